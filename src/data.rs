@@ -7,6 +7,43 @@ pub enum Usb2SnesError {
     NoGame
 }
 
+// What the game is doing, from the game state at $7E:0998
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GamePhase {
+    Menu,
+    Loading,
+    Gameplay,
+    DoorTransition,
+    Paused,
+    Death,
+    Demo,
+    Other
+}
+
+pub fn game_phase(game_state : u8) -> GamePhase {
+    match game_state {
+        0x00..=0x05 => GamePhase::Menu,
+        0x06 | 0x07 => GamePhase::Loading,
+        0x08 => GamePhase::Gameplay,
+        0x09..=0x0B => GamePhase::DoorTransition,
+        // Pause menus and reserve tanks refilling
+        0x0C..=0x12 | 0x1B => GamePhase::Paused,
+        0x13..=0x1A => GamePhase::Death,
+        // Going in and out of a demo loads its room
+        0x28 | 0x29 | 0x2B | 0x2C => GamePhase::Loading,
+        0x2A => GamePhase::Demo,
+        _ => GamePhase::Other
+    }
+}
+
+// What the renderer draws : the game camera, the camera centered on Samus (C) or the whole room (F)
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum View {
+    Game,
+    Samus,
+    Room
+}
+
 pub static RED : Color = Color{a : 0, r : 255, g : 0, b : 0};
 pub static GREEN : Color = Color{a : 0, r : 0, g : 255, b : 0};
 pub static GREY : Color = Color{a : 0, r : 0x80, g : 0x80, b : 0x80};
@@ -17,8 +54,119 @@ pub static AQUA : Color = Color{a : 0, r : 0, g : 255, b : 255};
 pub static PINK : Color = Color{a : 0, r : 255, g : 0xC0, b : 0xCB};
 pub static BLACK : Color = Color{a : 0, r : 0, g : 0, b : 0};
 pub static YELLOW : Color = Color{a : 0, r : 255, g : 255, b : 0};
+pub static SHOT_PURPLE : Color = Color{a : 0, r : 0xB5, g : 0x1D, b : 0xC3};
+pub static SHOT_PINK : Color = Color{a : 0, r : 0xF4, g : 0xD8, b : 0xF6};
+pub static ORANGE : Color = Color{a : 0, r : 255, g : 0x8C, b : 0};
+pub static MAGENTA : Color = Color{a : 0, r : 255, g : 0, b : 255};
+pub static SCROLL_RED : Color = Color{a : 0x50, r : 255, g : 0, b : 0};
+pub static SCROLL_BLUE : Color = Color{a : 0x50, r : 0, g : 0x60, b : 255};
+pub static SCROLL_GREEN : Color = Color{a : 0x50, r : 0, g : 255, b : 0};
+
+
+
+pub fn block_type_name(block_type : u8) -> &'static str {
+    match block_type {
+        0x0 => "Air",
+        0x1 => "Slope",
+        0x2 => "X-Ray air",
+        0x3 => "Treadmill",
+        0x4 => "Shootable air",
+        0x5 => "Horizontal extension",
+        0x6 => "Denied X-Ray",
+        0x7 => "Bombable air",
+        0x8 => "Solid",
+        0x9 => "Door",
+        0xA => "Spike",
+        0xB => "Crumble",
+        0xC => "Shot block",
+        0xD => "Vertical extension",
+        0xE => "Grapple block",
+        _ => "Bomb block"
+    }
+}
 
+fn block_size_name(bts : u8) -> &'static str {
+    match bts & 0x03 {
+        0 => "1x1",
+        1 => "2x1",
+        2 => "1x2",
+        _ => "2x2"
+    }
+}
+
+// Marker drawn after the glyph for blocks bigger than one tile
+fn block_size_marker(bts : u8) -> &'static str {
+    match bts & 0x03 {
+        0 => "",
+        1 => "-",
+        2 => "|",
+        _ => "+"
+    }
+}
 
+// What breaks a crumble, shot, grapple or bomb block, decoded from its BTS
+pub struct BreakableBlock {
+    pub name : &'static str,
+    pub glyph : &'static str,
+    pub color : Color,
+    pub text_color : Color,
+    pub size : Option<u8>,
+    pub respawn : bool
+}
+
+pub fn decode_breakable(block_type : u8, bts : u8) -> Option<BreakableBlock> {
+    let block = |name, glyph, color, text_color, size, respawn| Some(BreakableBlock { name, glyph, color, text_color, size, respawn });
+    let sized = |bts : u8| Some(bts & 0x03);
+    match (block_type, bts) {
+        (0xB, 0x00..=0x07) => block("crumble block", "C", GREY, WHITE, sized(bts), bts & 0x04 == 0),
+        (0xB, 0x0E) => block("speed booster block", ">", AQUA, AQUA, None, true),
+        (0xB, 0x0F) => block("speed booster block", ">", AQUA, AQUA, None, false),
+        (0xC, 0x00..=0x07) => block("shot block", "S", SHOT_PURPLE, SHOT_PINK, sized(bts), bts & 0x04 == 0),
+        (0xC, 0x0A) => block("super missile block", "M", GREEN, LIGHT_GREEN, None, true),
+        (0xC, 0x0B) => block("super missile block", "M", GREEN, LIGHT_GREEN, None, false),
+        (0xC, 0x0E) => block("power bomb block", "P", YELLOW, YELLOW, None, true),
+        (0xC, 0x0F) => block("power bomb block", "P", YELLOW, YELLOW, None, false),
+        (0xE, 0x00) => block("grapple block", "G", GREY, WHITE, None, true),
+        (0xE, 0x01) => block("crumbling grapple block", "G", GREY, PINK, None, true),
+        (0xE, 0x02) => block("crumbling grapple block", "G", GREY, PINK, None, false),
+        (0xF, 0x00..=0x07) => block("bomb block", "B", GREY, WHITE, sized(bts), bts & 0x04 == 0),
+        _ => None
+    }
+}
+
+// Text drawn on a breakable block : its glyph and size marker, or the default glyph and the BTS
+// when we don't know it
+pub fn breakable_label(block_type : u8, bts : u8, default_glyph : &str) -> String {
+    match decode_breakable(block_type, bts) {
+        Some(block) => format!("{}{}", block.glyph, block_size_marker(block.size.unwrap_or(0))),
+        None => format!("{}{:02X}", default_glyph, bts)
+    }
+}
+
+// What the BTS means for this block type
+pub fn bts_description(block_type : u8, bts : u8) -> String {
+    if let Some(block) = decode_breakable(block_type, bts) {
+        let size = match block.size {
+            Some(size) => format!("{} ", block_size_name(size)),
+            None => String::new()
+        };
+        // A grapple block never breaks
+        if block_type == 0xE && bts == 0 {
+            return String::from("Grapple block");
+        }
+        return format!("{}{}, {}", size, block.name, if block.respawn { "respawning" } else { "not respawning" });
+    }
+    match block_type {
+        0x1 => format!("Slope ${:02X}{}{}", bts & 0x1F, if bts & 0x40 != 0 { ", h-flip" } else { "" }, if bts & 0x80 != 0 { ", v-flip" } else { "" }),
+        0x5 | 0xD => format!("Extension, offset {}", bts as i8),
+        0x9 => format!("Door index {}", bts),
+        0xB => format!("Crumble ${:02X}", bts),
+        0xC => format!("Shot block ${:02X}", bts),
+        0xE => format!("Grapple ${:02X}", bts),
+        0xF => format!("Bomb block ${:02X}", bts),
+        _ => format!("BTS ${:02X}", bts)
+    }
+}
 
 pub fn usb2snes_to_string(err : Usb2SnesError) -> String {
     match err {
@@ -29,20 +177,53 @@ pub fn usb2snes_to_string(err : Usb2SnesError) -> String {
         Usb2SnesError::NoGame => {String::from("No game present")}
     }
 }
+#[derive(Clone)]
 pub struct SharedData {
     pub usb2snes_ready : bool,
     pub usb2snes_error : Usb2SnesError,
-    pub rom_data : Vec<u8>,
-    pub map_data : Vec<u8>,
+    pub rom_data : Rom,
+    // Shared with the published snapshots, see FrameChannel
+    pub map_data : Arc<Vec<u8>>,
     pub samus_pos : sdl2::rect::Point,
     pub camera : sdl2::rect::Point,
-    pub map_id : u8,
+    pub game_state : u8,
+    pub game_phase : GamePhase,
+    // Room pointer of the level data in map_data, None while going to another room
+    pub loaded_room : Option<u16>,
     pub radius : sdl2::rect::Point,
     pub width : u16,
+    pub height : u16,
     pub clip: u16,
     pub bts : u16,
     pub bts_byte : u8,
-    pub door_stuff : u16
+    pub door_stuff : u16,
+    pub room : Option<RoomHeader>,
+    // The overlays and the trail are shared by the published snapshots, not copied
+    pub enemies : Arc<Vec<Enemy>>,
+    pub projectiles : Arc<Vec<Projectile>>,
+    pub enemy_projectiles : Arc<Vec<EnemyProjectile>>,
+    pub plms : Arc<Vec<Plm>>,
+    // Scroll of each screen : 0 red, 1 blue, 2 green
+    pub scrolls : Arc<Vec<u8>>,
+    // Top left of the screen, where the game camera is
+    pub layer1 : sdl2::rect::Point,
+    pub movement : SamusMovement,
+    // Samus position and radius of the last frames, oldest first
+    pub trail : Arc<VecDeque<(sdl2::rect::Point, sdl2::rect::Point)>>,
+    // Game frame counter of this snapshot and the frames we never got
+    pub frame_counter : Option<u16>,
+    pub dropped_frames : u64,
+    // Level data as it was when we entered the room, to see the blocks that changed
+    pub room_map_data : Arc<Vec<u8>>,
+    // Frames between two reads of the visible level data, 0 to never read it again
+    pub level_refresh : u16,
+    pub level_refreshed_at : Option<u16>,
+    // The whole room is refreshed a few rows at a time, starting from this one
+    pub room_refresh_row : i32,
+    // The rows refreshed are the ones of this view, the renderer sets it through the FrameChannel
+    pub view : View,
+    // Incremented each time the polling thread publishes the data
+    pub version : u64
 }
 
 pub struct GameTileData {
@@ -53,90 +234,277 @@ pub struct GameTileData {
     pub clip : usize,
     pub clip_value : u16,
     pub door_stuff : u16,
-    pub bts_byte : u8
+    pub bts_byte : u8,
+    pub slope_columns : Option<[u8; 16]>
 }
 
+pub const TRAIL_LENGTH : usize = 120;
+
 impl SharedData {
+    // Add Samus current position to the trail, dropping the oldest one.
+    // The trail is copied when a published snapshot still uses it
+    pub fn push_trail(&mut self) {
+        let entry = (self.samus_pos, self.radius);
+        let trail = Arc::make_mut(&mut self.trail);
+        if trail.len() == TRAIL_LENGTH {
+            trail.pop_front();
+        }
+        trail.push_back(entry);
+    }
+    // Tag the snapshot with the game frame counter, true if it is a new frame.
+    // Frames between the last one and this one were dropped
+    pub fn set_frame_counter(&mut self, frame_counter : u16) -> bool {
+        let new_frame = match self.frame_counter {
+            Some(last) => {
+                let elapsed = frame_counter.wrapping_sub(last);
+                // A counter going back is a reset or a loaded state, not dropped frames
+                if elapsed > 1 && elapsed < 0x8000 {
+                    self.dropped_frames += elapsed as u64 - 1;
+                }
+                elapsed != 0
+            },
+            None => true
+        };
+        self.frame_counter = Some(frame_counter);
+        new_frame
+    }
     pub fn new() -> SharedData {
         SharedData { 
             usb2snes_error: Usb2SnesError::None,
-            rom_data: vec![],
-            map_data: vec![],
+            rom_data: Rom::new(),
+            map_data: Arc::new(vec![]),
             samus_pos: sdl2::rect::Point::new(0, 0),
             camera: sdl2::rect::Point::new(0, 0),
-            map_id: 0,
+            game_state: 0,
+            game_phase: GamePhase::Menu,
+            loaded_room: None,
             radius: sdl2::rect::Point::new(0, 0),
             width: 0,
+            height: 0,
             clip: 0,
             bts: 0,
             door_stuff : 0,
             usb2snes_ready: false,
-            bts_byte : 0
+            bts_byte : 0,
+            room : None,
+            enemies : Arc::new(vec![]),
+            projectiles : Arc::new(vec![]),
+            enemy_projectiles : Arc::new(vec![]),
+            plms : Arc::new(vec![]),
+            scrolls : Arc::new(vec![]),
+            layer1 : sdl2::rect::Point::new(0, 0),
+            movement : SamusMovement::default(),
+            trail : Arc::new(VecDeque::with_capacity(TRAIL_LENGTH)),
+            frame_counter : None,
+            dropped_frames : 0,
+            room_map_data : Arc::new(vec![]),
+            level_refresh : 10,
+            level_refreshed_at : None,
+            room_refresh_row : 0,
+            view : View::Game,
+            version : 0
         }
     }
 }
 
-use std::{collections::HashMap};
+impl Default for SharedData {
+    fn default() -> SharedData {
+        SharedData::new()
+    }
+}
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
 //use crate::gamearea::GameArea;
 use sdl2::pixels::Color;
 
 use crate::mycanvas::MyCanvas;
-#[macro_use]
+use crate::rom::Rom;
+use crate::room::RoomHeader;
+use crate::enemy::Enemy;
+use crate::projectile::{Projectile, EnemyProjectile};
+use crate::plm::Plm;
+use crate::samus::SamusMovement;
 use lazy_static::lazy_static;
 
-fn slope00(canvas : &mut MyCanvas, tile_x : i32, tile_y : i32, h_flip : i32, v_flip : i32) {
-    canvas.draw_game_box_fill(tile_x + 16 * h_flip, tile_y + 8 + 24 * v_flip, 16, 8, LIGHT_GREEN, GREY);
+// Shapes of the slopes (BTS & 0x1F) without flip, in pixels inside the 16x16 tile, solid part at the bottom right.
+// 0x00-0x03 and 0x07 are the square slopes made of 8x8 quarters, 0x12 and up are from the slope height tables.
+// The other ids aren't used by the game.
+lazy_static! {
+    static ref SLOPES : HashMap<u8, Vec<(i32, i32)>> = {
+        let mut s : HashMap<u8, Vec<(i32, i32)>> = HashMap::new();
+        // Half blocks and quarters
+        s.insert(0x00, vec![(0, 8), (16, 8), (16, 16), (0, 16)]);
+        s.insert(0x01, vec![(8, 0), (16, 0), (16, 16), (8, 16)]);
+        s.insert(0x02, vec![(8, 8), (16, 8), (16, 16), (8, 16)]);
+        s.insert(0x03, vec![(8, 0), (16, 0), (16, 16), (0, 16), (0, 8), (8, 8)]);
+        s.insert(0x07, vec![(0, 0), (16, 0), (16, 16), (0, 16)]);
+        // 45 degrees, full tile and half tile steps
+        s.insert(0x12, vec![(0, 16), (16, 0), (16, 16)]);
+        s.insert(0x13, vec![(0, 0), (16, 0), (16, 16), (0, 16)]);
+        s.insert(0x14, vec![(8, 16), (16, 8), (16, 16)]);
+        s.insert(0x15, vec![(0, 16), (0, 8), (8, 0), (16, 0), (16, 16)]);
+        // Gentle slope over 2 tiles
+        s.insert(0x16, vec![(0, 16), (16, 8), (16, 16)]);
+        s.insert(0x17, vec![(0, 16), (0, 8), (16, 0), (16, 16)]);
+        // Gentler slope over 3 tiles
+        s.insert(0x18, vec![(0, 16), (16, 11), (16, 16)]);
+        s.insert(0x19, vec![(0, 16), (0, 11), (16, 5), (16, 16)]);
+        s.insert(0x1A, vec![(0, 16), (0, 5), (16, 0), (16, 16)]);
+        // Steep slope over 2 tiles high
+        s.insert(0x1B, vec![(0, 16), (8, 0), (16, 0), (16, 16)]);
+        s.insert(0x1C, vec![(8, 16), (16, 0), (16, 16)]);
+        // Steeper slope over 3 tiles high
+        s.insert(0x1D, vec![(0, 16), (5, 0), (16, 0), (16, 16)]);
+        s.insert(0x1E, vec![(5, 16), (11, 0), (16, 0), (16, 16)]);
+        s.insert(0x1F, vec![(11, 16), (16, 0), (16, 16)]);
+        s
+    };
 }
 
-fn slope01(canvas : &mut MyCanvas, tile_x : i32, tile_y : i32, h_flip : i32, v_flip : i32) {
-    canvas.draw_game_box_fill(tile_x + 8 + 24 * h_flip, tile_y + 16 * v_flip, 8, 16, LIGHT_GREEN,GREY);
+// Shape of a slope tile with the BTS flips applied : 0x40 is horizontal, 0x80 is vertical
+pub fn slope_shape(bts : u8) -> Option<Vec<(i32, i32)>> {
+    let shape = SLOPES.get(&(bts & 0x1F))?;
+    Some(shape.iter().map(|&(x, y)| {
+        let x = if bts & 0x40 != 0 { 16 - x } else { x };
+        let y = if bts & 0x80 != 0 { 16 - y } else { y };
+        (x, y)
+    }).collect())
 }
 
-fn slope12(canvas : &mut MyCanvas, tile_x : i32, tile_y : i32, h_flip : i32, v_flip : i32) {
-    canvas.set_draw_color(LIGHT_GREEN);
-    canvas.draw_game_line(tile_x, tile_y + 16 * v_flip, tile_x + 16 * h_flip, tile_y);
-    //canvas.set_draw_color(WHITE);
-    canvas.draw_game_line(tile_x + 16 * h_flip, tile_y, tile_x + 16 * h_flip, tile_y + 16 * v_flip);
-    //canvas.set_draw_color(RED);
-    canvas.draw_game_line(tile_x + 16 * h_flip, tile_y + 16 * v_flip, tile_x, tile_y + 16 * v_flip);
-    canvas.set_draw_color(BLACK);
+// Per column slope tables in bank $94, 16 bytes per slope id.
+// Each byte is the y of the top of the solid part in that column of the tile
+const SLOPE_HEIGHT_TABLE : u32 = 0x948B2B;
+
+// Height of the solid part of each pixel column of a slope tile, from the ROM, with the BTS flips applied.
+// Only the real slopes (0x12 and up) use the table, the square ones are exact with slope_shape
+pub fn slope_columns(rom : &Rom, bts : u8) -> Option<[u8; 16]> {
+    let slope_id = bts & 0x1F;
+    if slope_id < 0x12 {
+        return None;
+    }
+    let table = rom.read_bytes(SLOPE_HEIGHT_TABLE + slope_id as u32 * 16, 16)?;
+    let mut columns = [0u8; 16];
+    for (x, column) in columns.iter_mut().enumerate() {
+        let source = if bts & 0x40 != 0 { 15 - x } else { x };
+        *column = 16 - table[source].min(16);
+    }
+    Some(columns)
 }
 
-fn slope13(canvas : &mut MyCanvas, tile_x : i32, tile_y : i32, h_flip : i32, v_flip : i32) {
-    canvas.draw_game_box_fill(tile_x + 16 * h_flip, tile_y + 16 * v_flip, 16, 16, LIGHT_GREEN, GREY);
+lazy_static! {
+    // Name and Samus box color of each movement type ($7E:0A1F)
+    pub static ref MOVEMENT_TYPES : HashMap<u8, (&'static str, Color)> = {
+        let mut m : HashMap<u8, (&'static str, Color)> = HashMap::new();
+        m.insert(0x00, ("Standing", AQUA));
+        m.insert(0x01, ("Running", AQUA));
+        m.insert(0x02, ("Normal jumping", GREEN));
+        m.insert(0x03, ("Spin jumping", LIGHT_GREEN));
+        m.insert(0x04, ("Morph ball on ground", PINK));
+        m.insert(0x05, ("Crouching", AQUA));
+        m.insert(0x06, ("Falling", GREEN));
+        m.insert(0x08, ("Morph ball in air", PINK));
+        m.insert(0x0A, ("Knockback", RED));
+        m.insert(0x0E, ("Turning around", AQUA));
+        m.insert(0x0F, ("Crouch or morph transition", AQUA));
+        m.insert(0x10, ("Moonwalking", AQUA));
+        m.insert(0x11, ("Spring ball on ground", PINK));
+        m.insert(0x12, ("Spring ball in air", PINK));
+        m.insert(0x13, ("Spring ball falling", PINK));
+        m.insert(0x14, ("Wall jumping", YELLOW));
+        m.insert(0x15, ("Ran into a wall", AQUA));
+        m.insert(0x16, ("Grappling", SHOT_PURPLE));
+        m.insert(0x17, ("Turning around in air", GREEN));
+        m.insert(0x18, ("Turning around falling", GREEN));
+        m.insert(0x19, ("Damage boost", RED));
+        m.insert(0x1A, ("Grabbed by Draygon", RED));
+        m.insert(0x1B, ("Shinespark", ORANGE));
+        m
+    };
+}
+
+lazy_static! {
+    // Name of the poses ($7E:0A1C) without the facing direction, the left and right poses share it.
+    // Unused and less common poses aren't named
+    pub static ref POSES : HashMap<u16, &'static str> = {
+        let mut m : HashMap<u16, &'static str> = HashMap::new();
+        let poses : [(&[u16], &'static str); 46] = [
+            (&[0x00, 0x9B], "Facing forward"),
+            (&[0x01, 0x02], "Standing"),
+            (&[0x03, 0x04], "Standing aiming up"),
+            (&[0x05, 0x06], "Standing aiming diagonal up"),
+            (&[0x07, 0x08], "Standing aiming diagonal down"),
+            (&[0x09, 0x0A], "Running"),
+            (&[0x0B, 0x0C], "Running gun extended"),
+            (&[0x0D, 0x0E], "Running aiming up"),
+            (&[0x0F, 0x10], "Running aiming diagonal up"),
+            (&[0x11, 0x12], "Running aiming diagonal down"),
+            (&[0x13, 0x14], "Jumping gun extended"),
+            (&[0x15, 0x16], "Jumping aiming up"),
+            (&[0x17, 0x18], "Jumping aiming down"),
+            (&[0x19, 0x1A], "Spin jump"),
+            (&[0x1B, 0x1C], "Space jump"),
+            (&[0x1D, 0x41], "Morph ball"),
+            (&[0x1E, 0x1F], "Morph ball rolling"),
+            (&[0x25, 0x26], "Turning around"),
+            (&[0x27, 0x28], "Crouching"),
+            (&[0x29, 0x2A], "Falling"),
+            (&[0x2B, 0x2C], "Falling aiming up"),
+            (&[0x2D, 0x2E], "Falling aiming down"),
+            (&[0x2F, 0x30], "Turning around in air"),
+            (&[0x31, 0x32], "Morph ball in air"),
+            (&[0x35, 0x36], "Crouching transition"),
+            (&[0x37, 0x38], "Morphing transition"),
+            (&[0x3B, 0x3C], "Standing transition"),
+            (&[0x3D, 0x3E], "Unmorphing transition"),
+            (&[0x43, 0x44], "Turning around crouching"),
+            (&[0x49, 0x4A], "Moonwalking"),
+            (&[0x4B, 0x4C], "Jump transition"),
+            (&[0x4D, 0x4E], "Jumping"),
+            (&[0x4F, 0x50], "Damage boost"),
+            (&[0x51, 0x52], "Jumping forward"),
+            (&[0x53, 0x54], "Knockback"),
+            (&[0x55, 0x56], "Jump transition aiming up"),
+            (&[0x57, 0x58], "Jump transition aiming diagonal up"),
+            (&[0x59, 0x5A], "Jump transition aiming diagonal down"),
+            (&[0x81, 0x82], "Screw attack"),
+            (&[0x83, 0x84], "Wall jump"),
+            (&[0xA4, 0xA5], "Landing"),
+            (&[0xA6, 0xA7], "Landing from spin jump"),
+            (&[0xC7, 0xC8], "Shinespark wind-up"),
+            (&[0xC9, 0xCA], "Shinespark horizontal"),
+            (&[0xCB, 0xCC], "Shinespark vertical"),
+            (&[0xCD, 0xCE], "Shinespark diagonal")
+        ];
+        for (ids, name) in poses.iter() {
+            for id in ids.iter() {
+                m.insert(*id, *name);
+            }
+        }
+        m
+    };
 }
 
-fn slope14(canvas : &mut MyCanvas, tile_x : i32, tile_y : i32, h_flip : i32, v_flip : i32) {
-    canvas.set_draw_color(LIGHT_GREEN);
-    canvas.draw_game_line(tile_x + 8 * h_flip, tile_y + 16 * v_flip, tile_x + 16 * h_flip, tile_y + 8 * v_flip);
-    canvas.draw_game_line(tile_x + 16 * h_flip, tile_y + 8 * v_flip, tile_x + 16 * h_flip, tile_y + 16 * v_flip);
-    canvas.draw_game_line(tile_x + 16 * h_flip, tile_y + 16 * v_flip, tile_x + 8 * h_flip, tile_y + 16 * v_flip);
-    canvas.set_draw_color(BLACK);
+pub fn pose_name(pose : u16) -> &'static str {
+    POSES.get(&pose).copied().unwrap_or("Unknown pose")
 }
 
-fn slope15(canvas : &mut MyCanvas, tile_x : i32, tile_y : i32, h_flip : i32, v_flip : i32) {
-    canvas.set_draw_color(LIGHT_GREEN);
-    canvas.draw_game_line(tile_x + 8 * h_flip, tile_y, tile_x + 16 * h_flip, tile_y);
-    canvas.draw_game_line(tile_x + 16 * h_flip, tile_y, tile_x + 16 * h_flip, tile_y + 16 * v_flip);
-    canvas.draw_game_line(tile_x, tile_y + 8 * v_flip, tile_x + 7 * h_flip, tile_y);
-    canvas.draw_game_line(tile_x, tile_y + 8 * v_flip, tile_x, tile_y + 16 * v_flip);
-    canvas.draw_game_line(tile_x, tile_y + 16 * v_flip, tile_x + 16 * h_flip, tile_y + 16 * v_flip);
-    canvas.set_draw_color(BLACK);
+pub fn movement_type_name(movement_type : u8) -> String {
+    match MOVEMENT_TYPES.get(&movement_type) {
+        Some((name, _)) => name.to_string(),
+        None => format!("Movement ${:02X}", movement_type)
+    }
 }
 
+pub fn movement_type_color(movement_type : u8) -> Color {
+    MOVEMENT_TYPES.get(&movement_type).map(|(_, color)| *color).unwrap_or(AQUA)
+}
+
+pub type Outline = fn(&mut MyCanvas, &GameTileData) -> Result<(), String>;
+
 lazy_static! {
-    static ref SLOPES : HashMap<u8, fn(&mut MyCanvas, i32, i32, i32, i32)> = {
-        let mut s : HashMap<u8, fn(&mut MyCanvas, i32, i32, i32, i32)> = HashMap::new();
-        s.insert(0, slope00);
-        s.insert(1, slope01);
-        s.insert(0x12, slope12);
-        s.insert(0x13, slope13);
-        s.insert(0x14, slope14);
-        s.insert(0x15, slope15);
-        s
-    };
-    pub static ref OUTLINES : HashMap::<u8, fn(&mut MyCanvas, &GameTileData)> = {
-        let mut hash = HashMap::<u8, fn(&mut MyCanvas, &GameTileData)>::new();
+    pub static ref OUTLINES : HashMap::<u8, Outline> = {
+        let mut hash = HashMap::<u8, Outline>::new();
         hash.insert(0, outline00);
         hash.insert(1, outline01);
         hash.insert(2, outline02);
@@ -147,137 +515,159 @@ lazy_static! {
         hash.insert(7, outline07);
         hash.insert(8, outline08);
         hash.insert(9, outline09);
-        hash.insert(0xA, outline0A);
-        hash.insert(0xB, outline0B);
-        hash.insert(0xC, outline0C);
-        hash.insert(0xD, outline0D);
-        hash.insert(0xE, outline0E);
-        hash.insert(0xF, outline0F);
+        hash.insert(0xA, outline0a);
+        hash.insert(0xB, outline0b);
+        hash.insert(0xC, outline0c);
+        hash.insert(0xD, outline0d);
+        hash.insert(0xE, outline0e);
+        hash.insert(0xF, outline0f);
         return hash;
     };
 }
 
-fn outline00(_canvas : &mut MyCanvas, _game_tile_data : &GameTileData) {
-
+fn outline00(_canvas : &mut MyCanvas, _game_tile_data : &GameTileData) -> Result<(), String> {
+    Ok(())
 }
 
 // Slopes
-fn outline01(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    let mut v_flip = 0;
-    let mut h_flip = 0;
-    let mut new_tile_x = game_tile_data.tile_x;
-    let mut new_tile_y = game_tile_data.tile_y;
-    if game_tile_data.bts_value & 0x40 != 0 {
-        new_tile_x += 16;
-        h_flip = -1
-    } else {
-        h_flip = 1
-    }
-    if game_tile_data.bts_value & 0x80 != 0 {
-        new_tile_y += 16;
-        v_flip = -1
-    } else {
-        v_flip = 1
-    }
-    let slope_id = game_tile_data.bts_value & 0x1F;
-    if slope_id == 0x13 || slope_id == 0x00 || slope_id == 0x01 || slope_id == 0x07 {
-        if v_flip == 1 {
-            v_flip = 0
-        }
-        if h_flip == 1 {
-            h_flip = 0
+fn outline01(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    if let Some(columns) = game_tile_data.slope_columns {
+        // Exact collision from the ROM, solid from the floor or from the ceiling when v-flipped
+        let v_flip = game_tile_data.bts_value & 0x80 != 0;
+        for (x, height) in columns.iter().enumerate() {
+            if *height == 0 {
+                continue;
+            }
+            let x = game_tile_data.tile_x + x as i32;
+            let (top, bottom) = if v_flip { (0, *height as i32 - 1) } else { (16 - *height as i32, 15) };
+            canvas.set_draw_color(GREY);
+            canvas.draw_game_line(x, game_tile_data.tile_y + top, x, game_tile_data.tile_y + bottom)?;
+            let edge = game_tile_data.tile_y + if v_flip { bottom } else { top };
+            canvas.set_draw_color(LIGHT_GREEN);
+            canvas.draw_game_line(x, edge, x, edge)?;
         }
+        canvas.set_draw_color(BLACK);
+        return Ok(());
     }
-    if SLOPES.contains_key(&slope_id) {
-        SLOPES[&slope_id](canvas, new_tile_x, new_tile_y, h_flip, v_flip)
-    } else {
-        canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, LIGHT_GREEN);
-        canvas.draw_game_text(game_tile_data.tile_x + 1, game_tile_data.tile_y + 1, format!("{:02x}", slope_id).as_str(), LIGHT_GREEN);
+    match slope_shape(game_tile_data.bts_value) {
+        Some(shape) => {
+            let points : Vec<(i32, i32)> = shape.iter().map(|&(x, y)| (game_tile_data.tile_x + x, game_tile_data.tile_y + y)).collect();
+            canvas.draw_game_polygon(&points, LIGHT_GREEN, GREY)?;
+        },
+        None => {
+            canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, LIGHT_GREEN)?;
+            canvas.draw_game_text(game_tile_data.tile_x + 1, game_tile_data.tile_y + 1, format!("{:02x}", game_tile_data.bts_value & 0x1F).as_str(), LIGHT_GREEN)?;
+        }
     }
+    Ok(())
 }
 
 // X-RAY Air
-fn outline02(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, RED);
-    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "X", RED);
+fn outline02(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, RED)?;
+    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "X", RED)?;
+    Ok(())
 }
 
 // Threadmill
-fn outline03(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box_fill(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, Color::RGB(170, 0, 0), Color::RGB(85, 0, 0));
-    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "T", Color::RGB(128, 128, 128));
+fn outline03(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    canvas.draw_game_box_fill(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, Color::RGB(170, 0, 0), Color::RGB(85, 0, 0))?;
+    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "T", Color::RGB(128, 128, 128))?;
+    Ok(())
 }
 
 // Shootable air
-fn outline04(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, Color::RGB(0, 170, 0));
-    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "A", Color::RGB(128, 128, 128));
+fn outline04(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, Color::RGB(0, 170, 0))?;
+    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "A", Color::RGB(128, 128, 128))?;
+    Ok(())
 }
 
-// Horizontal extension, this is supposed to find the right tile of extension but this is more memory request~~
-fn outline05(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, Color::RGB(170, 0, 170));
-    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "H", Color::RGB(128, 128, 128));
+// Horizontal extension, only drawn when its parent block can't be found
+fn outline05(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, Color::RGB(170, 0, 170))?;
+    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "H", Color::RGB(128, 128, 128))?;
+    Ok(())
 }
 
 // Denied X-RAY
-fn outline06(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box_fill(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, Color::RGB(180, 180, 180), Color::RGB(85, 85, 85));
-    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "F", Color::RGB(128, 128, 128));
+fn outline06(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    canvas.draw_game_box_fill(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, Color::RGB(180, 180, 180), Color::RGB(85, 85, 85))?;
+    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "F", Color::RGB(128, 128, 128))?;
+    Ok(())
 }
 
 // Bombable air? niée?
-fn outline07(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, Color::RGB(170, 0, 170));
-    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "H", Color::RGB(128, 128, 128));
+fn outline07(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, Color::RGB(170, 0, 170))?;
+    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "H", Color::RGB(128, 128, 128))?;
+    Ok(())
 }
 
 // Solid
-fn outline08(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box_fill(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, WHITE, GREY).unwrap();
+fn outline08(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    canvas.draw_game_box_fill(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, WHITE, GREY)?;
+    Ok(())
 }
 
 // Doors
-fn outline09(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box_fill(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, RED, PINK);
-    canvas.draw_game_text(game_tile_data.tile_x + 1, game_tile_data.tile_y, format!("{:02X}", game_tile_data.bts_value).as_str(), RED);
+fn outline09(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    canvas.draw_game_box_fill(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, RED, PINK)?;
+    canvas.draw_game_text(game_tile_data.tile_x + 1, game_tile_data.tile_y, format!("{:02X}", game_tile_data.bts_value).as_str(), RED)?;
+    Ok(())
 }
 
 // Spike
-fn outline0A(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, GREY);
-    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "S", YELLOW);
+fn outline0a(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, GREY)?;
+    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "S", YELLOW)?;
+    Ok(())
 }
 
 // Crumble
-fn outline0B(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, GREY);
-    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "C", WHITE);
+fn outline0b(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    draw_breakable(canvas, game_tile_data, 0xB, "C")
 }
 
 // Shot Block
-fn outline0C(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, Color::RGB(0xB5, 0x1D, 0xC3,));
-    // We need to read more memory to get the type :(
-    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "S", Color::RGB(0xF4, 0xD8, 0xF6));
+fn outline0c(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    draw_breakable(canvas, game_tile_data, 0xC, "S")
 }
 
-// Vertical Extension
-fn outline0D(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, GREY);
-    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "V", GREY);
+// Outline and glyph come from the BTS, blocks that don't respawn are filled
+fn draw_breakable(canvas : &mut MyCanvas, game_tile_data : &GameTileData, block_type : u8, default_glyph : &str) -> Result<(), String> {
+    let label = breakable_label(block_type, game_tile_data.bts_value, default_glyph);
+    match decode_breakable(block_type, game_tile_data.bts_value) {
+        Some(block) => {
+            if block.respawn {
+                canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, block.color)?;
+            } else {
+                canvas.draw_game_box_fill(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, block.color, Color::RGB(block.color.r / 3, block.color.g / 3, block.color.b / 3))?;
+            }
+            let glyph_x = if label.len() == 1 { 4 } else { 1 };
+            canvas.draw_game_text(game_tile_data.tile_x + glyph_x, game_tile_data.tile_y - 1, &label, block.text_color)?;
+        },
+        None => {
+            canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, GREY)?;
+            canvas.draw_game_text(game_tile_data.tile_x, game_tile_data.tile_y - 1, &label, GREY)?;
+        }
+    }
+    Ok(())
 }
 
-// Grapple block
-fn outline0E(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, GREY);
-    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "G", WHITE);
+// Vertical Extension, only drawn when its parent block can't be found
+fn outline0d(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, GREY)?;
+    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "V", GREY)?;
+    Ok(())
 }
 
-fn outline0F(canvas : &mut MyCanvas, game_tile_data : &GameTileData) {
-    canvas.draw_game_box(game_tile_data.tile_x, game_tile_data.tile_y, 16, 16, GREY);
-    canvas.draw_game_text(game_tile_data.tile_x + 4, game_tile_data.tile_y - 1, "B", WHITE);
+// Grapple block
+fn outline0e(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    draw_breakable(canvas, game_tile_data, 0xE, "G")
 }
 
-
-
+// Bomb block
+fn outline0f(canvas : &mut MyCanvas, game_tile_data : &GameTileData) -> Result<(), String> {
+    draw_breakable(canvas, game_tile_data, 0xF, "B")
+}
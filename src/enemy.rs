@@ -0,0 +1,66 @@
+use crate::rom::Rom;
+
+pub const ENEMY_SLOTS : usize = 32;
+pub const ENEMY_SLOT_SIZE : usize = 0x40;
+
+// What we use of an enemy slot of the table at $7E:0F78
+#[derive(Clone)]
+pub struct Enemy {
+    pub slot : usize,
+    // Pointer to the enemy header in bank $A0
+    pub id : u16,
+    pub x : i32,
+    pub y : i32,
+    pub radius_x : i32,
+    pub radius_y : i32,
+    pub properties : u16,
+    pub health : u16
+}
+
+impl Enemy {
+    // Enemies that Samus and her shots go through
+    pub fn intangible(&self) -> bool {
+        self.properties & 0x0400 != 0
+    }
+    pub fn invisible(&self) -> bool {
+        self.properties & 0x0100 != 0
+    }
+}
+
+fn word(bytes : &[u8], offset : usize) -> u16 {
+    bytes[offset] as u16 + bytes[offset + 1] as u16 * 256
+}
+
+// Every used slot of the enemy table, slots with id 0 are free
+pub fn parse_enemies(bytes : &[u8]) -> Vec<Enemy> {
+    let mut enemies = vec![];
+    for (slot, data) in bytes.chunks_exact(ENEMY_SLOT_SIZE).take(ENEMY_SLOTS).enumerate() {
+        let id = word(data, 0x00);
+        if id == 0 {
+            continue;
+        }
+        enemies.push(Enemy {
+            slot,
+            id,
+            x : word(data, 0x02) as i32,
+            y : word(data, 0x06) as i32,
+            radius_x : word(data, 0x0A) as i32,
+            radius_y : word(data, 0x0C) as i32,
+            properties : word(data, 0x0E),
+            health : word(data, 0x14)
+        });
+    }
+    enemies
+}
+
+// The enemy header ends with a pointer to the debug name in bank $B4
+pub fn enemy_name(rom : &Rom, id : u16) -> String {
+    let name = rom.read_u16(0xA00000 + id as u32 + 0x3E)
+        .filter(|pointer| *pointer >= 0x8000)
+        .and_then(|pointer| rom.read_bytes(0xB40000 + pointer as u32, 10))
+        .map(|bytes| bytes.iter().take_while(|b| b.is_ascii_graphic() || **b == b' ').map(|b| *b as char).collect::<String>());
+    match name {
+        Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => format!("Enemy ${:04X}", id)
+    }
+}
@@ -0,0 +1,16 @@
+pub mod data;
+pub mod mycanvas;
+pub mod memorysource;
+pub mod savestate;
+pub mod recording;
+pub mod wsthread;
+pub mod mockserver;
+pub mod render;
+pub mod rom;
+pub mod room;
+pub mod enemy;
+pub mod projectile;
+pub mod plm;
+pub mod samus;
+pub mod snapshot;
+//pub mod gamearea;
@@ -2,43 +2,59 @@ extern crate sdl2;
 
 use std::sync::{Mutex, Arc};
 use data::{*};
-use sdl2::event::Event;
+use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
-use sdl2::pixels::{Color, PixelFormatEnum};
-use sdl2::rect::{Rect, Point};
-use sdl2::video::{Window};
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
 use sdl2::surface::Surface;
-use sdl2::render::{Canvas};
-use sdl2::ttf::{Sdl2TtfContext, Font};
-use std::time::{SystemTime, Duration};
+use std::time::{Duration, SystemTime};
 use std::env;
-mod wsthread;
-mod data;
-mod mycanvas;
-//mod gamearea;
+use smtileviewer::{wsthread, data, mycanvas, memorysource, savestate, recording, render, rom};
+use smtileviewer::snapshot::FrameChannel;
 
 
-macro_rules! rect(
-    ($x:expr, $y:expr, $w:expr, $h:expr) => (
-        Rect::new($x as i32, $y as i32, $w as u32, $h as u32)
-    )
-);
-
 fn main() -> Result<(), String> {
+    let args: Vec<_> = env::args().collect();
+    if args.len() > 1 && (args[1] == "render" || args[1] == "room") {
+        return render_command(&args[1], &args[2..]);
+    }
     let sdl_context = sdl2::init()?;
     let video_subsystem = sdl_context.video()?;
     let mut scale : f32 = 1.0;
     let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
 
-    let args: Vec<_> = env::args().collect();
-    if args.len() == 2 {
-        match &args[1].parse::<f32>() {
-            Ok(v) => { scale = *v},
-            Err(err) => {}
+    let mut savestate_file : Option<String> = None;
+    let mut rom_file : Option<String> = None;
+    let mut record_file : Option<String> = None;
+    let mut replay_file : Option<String> = None;
+    let mut samus_view = false;
+    let mut level_refresh : u16 = 10;
+    let mut i = 1;
+    while i < args.len() {
+        if args[i] == "--savestate" && i + 1 < args.len() {
+            savestate_file = Some(args[i + 1].clone());
+            i += 1;
+        } else if args[i] == "--rom" && i + 1 < args.len() {
+            rom_file = Some(args[i + 1].clone());
+            i += 1;
+        } else if args[i] == "--record" && i + 1 < args.len() {
+            record_file = Some(args[i + 1].clone());
+            i += 1;
+        } else if args[i] == "--replay" && i + 1 < args.len() {
+            replay_file = Some(args[i + 1].clone());
+            i += 1;
+        } else if args[i] == "--refresh" && i + 1 < args.len() {
+            level_refresh = args[i + 1].parse::<u16>().map_err(|_| format!("--refresh needs a number of frames, not {}", args[i + 1]))?;
+            i += 1;
+        } else if args[i] == "--samus-camera" {
+            samus_view = true;
+        } else if let Ok(v) = args[i].parse::<f32>() {
+            scale = v;
         }
+        i += 1;
     }
     let window = video_subsystem
-        .window("SM Tile viewer", (550.0 * scale) as u32, (520.0 * scale) as u32)
+        .window("SM Tile viewer", (render::CANVAS_WIDTH as f32 * scale) as u32, (render::CANVAS_HEIGHT as f32 * scale) as u32)
         .position_centered()
         .opengl()
         .build()
@@ -53,106 +69,206 @@ fn main() -> Result<(), String> {
     
     
     let mut event_pump = sdl_context.event_pump()?;
-    let datas = Arc::new(Mutex::<SharedData>::new(SharedData::new()));
+    let mut initial_data = SharedData::new();
+    initial_data.level_refresh = level_refresh;
+    let datas = Arc::new(FrameChannel::new(initial_data));
     let datas_clone = Arc::clone(&datas);
-    let wsthread = std::thread::spawn(move || {
-        wsthread::wsthread(datas_clone)
-    });
-    let mut game_tile_data : GameTileData = GameTileData { tile_x: 0, tile_y: 0, bts: 0, bts_value: 0, clip: 0, clip_value: 0, door_stuff : 0, bts_byte : 0 };
-    let mut status_font = ttf_context.load_font("FreeMonoBold.ttf", 16)?;
+    let replay_control = Arc::new(Mutex::new(recording::ReplayControl::new()));
+    let rom = match &rom_file {
+        Some(rom_path) if replay_file.is_some() || savestate_file.is_some() =>
+            rom::load_rom_file(rom_path).map_err(|e| format!("Can't load ROM {} : {}", rom_path, e))?,
+        _ => vec![]
+    };
+    let _wsthread = if let Some(path) = &replay_file {
+        let source = recording::ReplaySource::load(path, rom, Arc::clone(&replay_control)).map_err(|e| format!("Can't load recording {} : {}", path, e))?;
+        std::thread::spawn(move || {
+            wsthread::source_thread(source, datas_clone)
+        })
+    } else if let Some(path) = &savestate_file {
+        let wram = savestate::load_savestate_wram(path).map_err(|e| format!("Can't load savestate {} : {}", path, e))?;
+        let source = memorysource::MemoryImage::new(wram, rom);
+        std::thread::spawn(move || {
+            wsthread::source_thread(source, datas_clone)
+        })
+    } else {
+        std::thread::spawn(move || {
+            wsthread::wsthread(datas_clone, record_file, wsthread::RETRY_DELAY)
+        })
+    };
+    let status_font = ttf_context.load_font("FreeMonoBold.ttf", 16)?;
     //status_font.set_style(sdl2::ttf::FontStyle::BOLD);
-    let mut mycanvas = mycanvas::MyCanvas::new(&mut canvas, &ttf_context, &mut status_font);
+    let texture_creator = canvas.texture_creator();
+    let mut mycanvas = mycanvas::MyCanvas::new(render::CANVAS_WIDTH, render::CANVAS_HEIGHT, &ttf_context, &status_font)?;
+    let mut room_view = false;
+    let mut scroll_view = false;
+    let mut frozen_trail : Option<Arc<std::collections::VecDeque<(Point, Point)>>> = None;
+    let mut freeze_trail = false;
+    // Game frames displayed more than once or never displayed
+    let mut last_shown_frame : Option<u16> = None;
+    let mut shown_twice : u64 = 0;
+    let mut not_shown : u64 = 0;
+    let mut mouse = Point::new(-1, -1);
+    let mut export_room = false;
+    let mut room_surface : Option<(Arc<Vec<u8>>, Surface)> = None;
+    // Version of the snapshot on screen, it is drawn again only when it or the user changed something
+    let mut drawn_version : Option<u64> = None;
     'running: loop {
-        let start_loop_time = SystemTime::now();
+        let mut input = false;
         for event in event_pump.poll_iter() {
+            input = true;
             match event {
                 Event::Quit { .. }
                 | Event::KeyDown {
                     keycode: Some(Keycode::Escape),
                     ..
                 } => break 'running,
+                Event::MouseMotion { x, y, .. } => {
+                    mouse = Point::new((x as f32 / scale) as i32 - mycanvas::GAME_X_OFFSET, (y as f32 / scale) as i32 - mycanvas::GAME_Y_OFFSET);
+                },
+                Event::Window { win_event : WindowEvent::Leave, .. } => mouse = Point::new(-1, -1),
+                Event::KeyDown { keycode: Some(Keycode::F), .. } => room_view = !room_view,
+                Event::KeyDown { keycode: Some(Keycode::E), .. } => export_room = true,
+                Event::KeyDown { keycode: Some(Keycode::S), .. } => scroll_view = !scroll_view,
+                Event::KeyDown { keycode: Some(Keycode::C), .. } => samus_view = !samus_view,
+                Event::KeyDown { keycode: Some(Keycode::T), .. } => freeze_trail = !freeze_trail,
+                Event::KeyDown { keycode: Some(keycode), .. } if replay_file.is_some() => {
+                    let mut control = replay_control.lock().unwrap();
+                    match keycode {
+                        Keycode::Space => control.paused = !control.paused,
+                        Keycode::Period => control.step = 1,
+                        Keycode::Comma => control.step = -1,
+                        Keycode::Right => control.seek = 5000,
+                        Keycode::Left => control.seek = -5000,
+                        _ => {}
+                    }
+                },
                 _ => {}
             }
         }
-        let font_game = ttf_context.load_font("FreeMonoBold.ttf", 8)?;
-        /*let mut c = surf.into_canvas().unwrap();
-        let mut game_area = gamearea::GameArea::new(c, &font_game)?; */
-        mycanvas.clear();
-        let samus : sdl2::rect::Point;
-        let width : u16;
-        let camera : sdl2::rect::Point;
-        let draw_game : bool;
-        let radius : sdl2::rect::Point;
-        let usb2snes_status : String;
+        // The polling thread refreshes the level rows of what we draw
+        datas.set_view(if room_view { View::Room } else if samus_view { View::Samus } else { View::Game });
         let start_get_data_time = SystemTime::now();
-        let map_infos : Vec<u8>;
-        {
-            let mutex = datas.lock().unwrap();
-            draw_game = (*mutex).usb2snes_ready;
-            usb2snes_status = if (*mutex).usb2snes_ready {
-                String::from("Usb2Snes connection : Ready")
-            } else {
-                let err = (*mutex).usb2snes_error.clone();
-                let error_str = data::usb2snes_to_string(err);
-                format!("Usb2Snes connection : Not ready - {}", error_str)
-            };
-            samus = (*mutex).samus_pos;
-            width = (*mutex).width;
-            camera = (*mutex).camera;
-            radius = (*mutex).radius;
-            map_infos = (*mutex).map_data.clone();
-            game_tile_data.door_stuff = (*mutex).door_stuff.clone();
-        }
+        let mut frame = datas.latest();
         let data_time_duration = start_get_data_time.elapsed().unwrap();
+        if !input && drawn_version == Some(frame.version) {
+            std::thread::sleep(Duration::from_millis(1));
+            continue;
+        }
+        drawn_version = Some(frame.version);
+        mycanvas.clear();
+        let usb2snes_status = if replay_file.is_some() {
+            let control = replay_control.lock().unwrap();
+            format!("Replay : {:.1} s / {:.1} s{}", control.position as f32 / 1000.0, control.length as f32 / 1000.0,
+                if control.paused { " (paused)" } else { "" })
+        } else if let Some(path) = &savestate_file {
+            format!("Savestate : {}", path)
+        } else if frame.usb2snes_ready {
+            String::from("Usb2Snes connection : Ready")
+        } else {
+            let err = frame.usb2snes_error;
+            let error_str = data::usb2snes_to_string(err);
+            format!("Usb2Snes connection : Not ready - {}", error_str)
+        };
+        // The snapshot is shared with the polling thread, changing it makes our own copy
+        // (the level data stays shared)
+        if samus_view {
+            let camera = render::samus_camera(&frame);
+            Arc::make_mut(&mut frame).camera = camera;
+        }
+        // A frozen trail stays as it was when T was pressed
+        if freeze_trail {
+            match &frozen_trail {
+                Some(trail) => Arc::make_mut(&mut frame).trail = Arc::clone(trail),
+                None => frozen_trail = Some(Arc::clone(&frame.trail))
+            }
+        } else {
+            frozen_trail = None;
+        }
         mycanvas.draw_text(0, 0, usb2snes_status.as_str())?;
         //println!("{:?}", data_time_duration);
-        mycanvas.draw_text(0, 10, format!("Time to get datas : {} ms", data_time_duration.as_millis()).as_str())?;
-        if draw_game {
-            let top_left = Point::new(256 - radius.x, 224 - radius.y);
-            let bottom_right = Point::new(256 + radius.x, 224 + radius.y);
-            mycanvas.draw_text(0, 20, format!("Samus coord : {},{} Camera : {} , {} - Width : {}", samus.x, samus.y, camera.x, camera.y, width).as_str())?;
-            if map_infos.len() != 0 {
-            for y in 0..28 {
-                for x in 0..32 {
-                    let tile_x = x * 16 - (camera.x & 0x000F);
-                    let tile_y = y * 16 - (camera.y & 0x000F);
-                    let a : i32 = ((camera.x + x * 16) & 0xFFFF) / 16 + ((((camera.y + y * 16) & 0xFFF) / 16) * (width as i32) & 0xFFFF);
-                    let bts : usize = (0x6402 as usize + a as usize) % 0x10000;
-                    let bts_value = map_infos[bts];
-                    let clip : usize = (0x0002 + a as usize * 2) % 0x10000;
-                    let clip_value : u16 = map_infos[clip + 1] as u16  * 256 + map_infos[clip] as u16;
-                    let outline_index : u8 = (clip_value >> 12) as u8;
-                    game_tile_data.tile_x = tile_x;
-                    game_tile_data.tile_y = tile_y;
-                    game_tile_data.bts = bts;
-                    game_tile_data.clip = clip;
-                    game_tile_data.bts_value = bts_value;
-                    if OUTLINES.contains_key(&outline_index) {
-                        OUTLINES[&outline_index](&mut mycanvas, &game_tile_data);
-                    } else {
-                        mycanvas.draw_game_box_fill(tile_x, tile_y, 16, 16, DARK_GREEN, GREY)?;
-                        mycanvas.draw_game_text(tile_x + 3, tile_y - 1, format!("{:02x}", outline_index).as_str(), DARK_GREEN)?;
+        if let (Some(last), Some(current)) = (last_shown_frame, frame.frame_counter) {
+            match current.wrapping_sub(last) {
+                0 => shown_twice += 1,
+                elapsed if elapsed < 0x8000 => not_shown += elapsed as u64 - 1,
+                _ => {}
+            }
+        }
+        last_shown_frame = frame.frame_counter;
+        mycanvas.draw_text(0, 10, format!("Time to get datas : {} ms - Frames shown twice : {} not shown : {}", data_time_duration.as_millis(), shown_twice, not_shown).as_str())?;
+        if frame.usb2snes_ready {
+            if freeze_trail {
+                mycanvas.draw_text(400, 20, "Trail frozen")?;
+            }
+            if export_room {
+                let name = format!("room_{}x{}_{}", frame.width, frame.height, SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs());
+                if let Err(err) = render::export_room(&ttf_context, &status_font, &frame, &format!("{}.png", name), Some(&format!("{}.txt", name))) {
+                    println!("Can't export the room : {}", err);
+                }
+                export_room = false;
+            }
+            if room_view {
+                // Drawing the whole room is slow, only do it when the level data changes
+                let up_to_date = match &room_surface {
+                    Some((map_data, _)) => Arc::ptr_eq(map_data, &frame.map_data),
+                    None => false
+                };
+                if !up_to_date {
+                    room_surface = Some((frame.map_data.clone(), render::render_room(&ttf_context, &status_font, &frame)?));
+                }
+                if let Some((_, surface)) = &room_surface {
+                    render::draw_room_view(&mut mycanvas, surface, &frame)?;
+                    if scroll_view {
+                        render::draw_scrolls(&mut mycanvas, &frame, 0, 0, render::room_view_scale(surface))?;
                     }
                 }
+            } else {
+                render::draw_game(&mut mycanvas, &frame)?;
+                if scroll_view {
+                    render::draw_scrolls(&mut mycanvas, &frame, -frame.camera.x, -frame.camera.y, 1.0)?;
+                }
             }
+        }
+        render::draw_borders(&mut mycanvas)?;
+        if frame.usb2snes_ready {
+            render::draw_status(&mut mycanvas, &render::status_lines(&frame, samus_view))?;
+            if !room_view {
+                render::draw_tile_tooltip(&mut mycanvas, &frame, mouse.x, mouse.y)?;
             }
-            mycanvas.draw_game_box_fill(256 - radius.x, 224 - radius.y, radius.x * 2, radius.y * 2, AQUA, AQUA)?;
         }
-        mycanvas.draw_box_cord_fill(0, 50, 18, 600, BLACK, BLACK)?;
-        mycanvas.draw_box_cord_fill(0, 40, 580, 28, BLACK, BLACK)?;
-        mycanvas.draw_box_cord_fill(518, 50, 18, 600, BLACK, BLACK)?;
-        mycanvas.draw_box_cord_fill(0, 495, 580, 28, BLACK, BLACK)?;
-        /*let game_surface = game_area.into_surface();
-        let tex_creator = mycanvas.texture_creator();
-        let game_texture = tex_creator.create_texture_from_surface(game_surface).unwrap();
-        mycanvas.copy(&game_texture, None, Some(rect!(0, 50, 32 * 16, 28 * 16)))?;*/
-        mycanvas.present();
-        /*let d_60fps = Duration::new(0, 1_000_000_000u32 / 60);
-        let end_draw_time = start_loop_time.elapsed().unwrap();
-        if end_draw_time < d_60fps {
-            std::thread::sleep(d_60fps - end_draw_time);
-        }*/
+        let texture = texture_creator.create_texture_from_surface(mycanvas.surface()).map_err(|e| e.to_string())?;
+        canvas.copy(&texture, None, None)?;
+        canvas.present();
     }
 
     Ok(())
 }
+
+// SMTileViewer render --wram dump.bin --out frame.png
+// SMTileViewer room --wram dump.bin --out room.png [--dump room.txt]
+fn render_command(command : &str, args : &[String]) -> Result<(), String> {
+    let mut wram_file : Option<&String> = None;
+    let mut out_file : Option<&String> = None;
+    let mut dump_file : Option<&str> = None;
+    let mut rom_file : Option<&str> = None;
+    let mut i = 0;
+    while i + 1 < args.len() {
+        match args[i].as_str() {
+            "--wram" | "--savestate" => wram_file = Some(&args[i + 1]),
+            "--out" => out_file = Some(&args[i + 1]),
+            "--dump" => dump_file = Some(&args[i + 1]),
+            "--rom" => rom_file = Some(&args[i + 1]),
+            _ => return Err(format!("Unknown option {}", args[i]))
+        }
+        i += 2;
+    }
+    match (wram_file, out_file) {
+        (Some(wram), Some(out)) => {
+            if command == "room" {
+                render::export_room_from_wram(wram, rom_file, out, dump_file)
+            } else {
+                render::render_to_png(wram, rom_file, out)
+            }
+        },
+        _ => Err(String::from("Usage : SMTileViewer render --wram dump.bin [--rom sm.sfc] --out frame.png\n        SMTileViewer room --wram dump.bin [--rom sm.sfc] --out room.png [--dump room.txt]"))
+    }
+}
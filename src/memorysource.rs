@@ -0,0 +1,154 @@
+use std::fmt;
+use rusb2snes::SyncClient;
+
+// Error of a memory source, tungstenite's error is boxed as it is large
+#[derive(Debug)]
+pub enum Error {
+    Usb2Snes(Box<tungstenite::Error>),
+    Io(std::io::Error)
+}
+
+impl From<tungstenite::Error> for Error {
+    fn from(error : tungstenite::Error) -> Error {
+        Error::Usb2Snes(Box::new(error))
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(error : std::io::Error) -> Error {
+        Error::Io(error)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f : &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Usb2Snes(error) => write!(f, "{}", error),
+            Error::Io(error) => write!(f, "{}", error)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// Addresses are in the usb2snes address space : the ROM starts at 0x000000
+// and the WRAM (0x7E0000-0x7FFFFF on the SNES side) is at 0xF50000-0xF6FFFF
+pub const WRAM_BASE : u32 = 0xF50000;
+pub const WRAM_SIZE : usize = 0x20000;
+
+// Convert a SNES WRAM address (0x7E0000-0x7FFFFF) to the usb2snes address space
+pub fn wram(address : u32) -> u32 {
+    address - 0x7E0000 + WRAM_BASE
+}
+
+// Several blocks are read with the SD2SNES VGET command : at most 8 blocks of at most 255 bytes
+pub const VGET_BLOCKS : usize = 8;
+pub const VGET_BLOCK_SIZE : usize = 255;
+
+// Cut the blocks of a read_multi in pieces and group them in requests the SD2SNES accepts,
+// the data of the requests appended in order is the data of the blocks
+pub fn vget_requests(addresses : &[u32], sizes : &[usize]) -> Vec<(Vec<u32>, Vec<usize>)> {
+    let mut requests : Vec<(Vec<u32>, Vec<usize>)> = vec![];
+    let mut request : (Vec<u32>, Vec<usize>) = (vec![], vec![]);
+    for (address, size) in addresses.iter().zip(sizes.iter()) {
+        let mut offset = 0;
+        while offset < *size {
+            let piece = (size - offset).min(VGET_BLOCK_SIZE);
+            if request.0.len() == VGET_BLOCKS {
+                requests.push(std::mem::take(&mut request));
+            }
+            request.0.push(address + offset as u32);
+            request.1.push(piece);
+            offset += piece;
+        }
+    }
+    if !request.0.is_empty() {
+        requests.push(request);
+    }
+    requests
+}
+
+// Something we can read the game memory from, usb2snes, a savestate, a recording...
+pub trait MemorySource {
+    fn read(&mut self, address : u32, size : usize) -> Result<Vec<u8>, Error>;
+
+    // Read several blocks at once, the result is all the blocks appended in order
+    fn read_multi(&mut self, addresses : &[u32], sizes : &[usize]) -> Result<Vec<u8>, Error> {
+        let mut bytes : Vec<u8> = vec![];
+        for (address, size) in addresses.iter().zip(sizes.iter()) {
+            bytes.extend(self.read(*address, *size)?);
+        }
+        Ok(bytes)
+    }
+
+    // Can the ROM be read from this source
+    fn has_rom(&self) -> bool {
+        true
+    }
+}
+
+impl MemorySource for SyncClient {
+    fn read(&mut self, address : u32, size : usize) -> Result<Vec<u8>, Error> {
+        Ok(self.get_address(address, size)?)
+    }
+    fn read_multi(&mut self, addresses : &[u32], sizes : &[usize]) -> Result<Vec<u8>, Error> {
+        let mut bytes : Vec<u8> = vec![];
+        for (addresses, sizes) in vget_requests(addresses, sizes) {
+            bytes.extend(self.get_multi_address_as_u8(addresses, sizes)?);
+        }
+        Ok(bytes)
+    }
+}
+
+// The usb2snes commands used to find a device running a game before reading it
+pub trait Usb2SnesClient : MemorySource {
+    fn list_devices(&mut self) -> Result<Vec<String>, Error>;
+    fn attach_device(&mut self, device : &str) -> Result<(), Error>;
+    // Path of the file the device runs, /boot/menu.bin when it is in its menu
+    fn game(&mut self) -> Result<String, Error>;
+}
+
+impl Usb2SnesClient for SyncClient {
+    fn list_devices(&mut self) -> Result<Vec<String>, Error> {
+        Ok(self.list_device()?)
+    }
+    fn attach_device(&mut self, device : &str) -> Result<(), Error> {
+        Ok(self.attach(&device.to_string())?)
+    }
+    fn game(&mut self) -> Result<String, Error> {
+        Ok(self.info()?.game)
+    }
+}
+
+// Plain memory dump, used for offline sources and tests
+pub struct MemoryImage {
+    pub wram : Vec<u8>,
+    pub rom : Vec<u8>
+}
+
+impl MemoryImage {
+    pub fn new(wram : Vec<u8>, rom : Vec<u8>) -> MemoryImage {
+        MemoryImage {
+            wram,
+            rom
+        }
+    }
+}
+
+impl MemorySource for MemoryImage {
+    fn read(&mut self, address : u32, size : usize) -> Result<Vec<u8>, Error> {
+        let (memory, offset) = if address >= WRAM_BASE {
+            (&self.wram, (address - WRAM_BASE) as usize)
+        } else {
+            (&self.rom, address as usize)
+        };
+        if offset + size > memory.len() {
+            return Err(Error::Io(std::io::Error::new(std::io::ErrorKind::UnexpectedEof,
+                format!("Reading {:x} bytes at {:06x} is out of the memory image", size, address))));
+        }
+        Ok(memory[offset..offset + size].to_vec())
+    }
+    fn has_rom(&self) -> bool {
+        !self.rom.is_empty()
+    }
+}
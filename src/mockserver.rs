@@ -0,0 +1,205 @@
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, Mutex, MutexGuard};
+use serde::Deserialize;
+use tungstenite::stream::MaybeTlsStream;
+use tungstenite::{accept, connect, Message, WebSocket};
+use crate::memorysource::{Error, MemorySource, MemoryImage, Usb2SnesClient, VGET_BLOCKS, VGET_BLOCK_SIZE, vget_requests};
+
+// A tiny usb2snes server answering DeviceList, Attach, Info and GetAddress
+// from a memory image, so the connection logic can be tested without a SNES.
+// Each server gets its own free port, connect to it with MockClient,
+// or start it on the usb2snes port to test the real SyncClient.
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct Query {
+    Opcode : String,
+    Operands : Vec<String>
+}
+
+pub struct MockState {
+    pub devices : Vec<String>,
+    pub game : String,
+    // Drop the connection when asked for the devices
+    pub fail_device_list : bool,
+    pub memory : MemoryImage
+}
+
+pub struct MockServer {
+    state : Arc<Mutex<MockState>>,
+    address : SocketAddr
+}
+
+impl MockServer {
+    pub fn start(memory : MemoryImage) -> Result<MockServer, std::io::Error> {
+        MockServer::start_on(0, memory)
+    }
+    pub fn start_on(port : u16, memory : MemoryImage) -> Result<MockServer, std::io::Error> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        let address = listener.local_addr()?;
+        let state = Arc::new(Mutex::new(MockState {
+            devices : vec![],
+            game : String::from("/boot/menu.bin"),
+            fail_device_list : false,
+            memory
+        }));
+        let state_clone = Arc::clone(&state);
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let state = Arc::clone(&state_clone);
+                std::thread::spawn(move || {
+                    if let Ok(ws) = accept(stream) {
+                        let _ = handle_client(ws, &state);
+                    }
+                });
+            }
+        });
+        Ok(MockServer {
+            state,
+            address
+        })
+    }
+    pub fn address(&self) -> SocketAddr {
+        self.address
+    }
+    pub fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+}
+
+fn handle_client(mut ws : WebSocket<TcpStream>, state : &Mutex<MockState>) -> Result<(), Error> {
+    loop {
+        let text = match ws.read()? {
+            Message::Text(text) => text,
+            Message::Close(_) => return Ok(()),
+            _ => continue
+        };
+        let query : Query = match serde_json::from_str(&text) {
+            Ok(query) => query,
+            Err(_) => continue
+        };
+        let mut state = state.lock().unwrap();
+        match query.Opcode.as_str() {
+            "DeviceList" => {
+                if state.fail_device_list {
+                    return Ok(());
+                }
+                reply(&mut ws, state.devices.clone())?;
+            },
+            "Info" => {
+                let game = state.game.clone();
+                reply(&mut ws, vec![String::from("1.10.0"), String::from("SD2SNES"), game])?;
+            },
+            "GetAddress" => {
+                let pairs : Vec<(u32, usize)> = query.Operands.chunks(2).map(|pair| {
+                    (u32::from_str_radix(&pair[0], 16).unwrap_or(0), usize::from_str_radix(&pair[1], 16).unwrap_or(0))
+                }).collect();
+                // Several blocks are a VGET, drop the connection when the SD2SNES would refuse it
+                if pairs.len() > 1 && (pairs.len() > VGET_BLOCKS || pairs.iter().any(|p| p.1 > VGET_BLOCK_SIZE)) {
+                    return Ok(());
+                }
+                let mut data : Vec<u8> = vec![];
+                for (address, size) in pairs {
+                    match state.memory.read(address, size) {
+                        Ok(bytes) => data.extend(bytes),
+                        Err(_) => data.extend(vec![0; size])
+                    }
+                }
+                // Like the real thing, send the data in 1024 bytes chunks
+                for chunk in data.chunks(1024) {
+                    ws.send(Message::binary(chunk))?;
+                }
+            },
+            // Name and Attach don't have a reply
+            _ => {}
+        }
+    }
+}
+
+fn reply(ws : &mut WebSocket<TcpStream>, results : Vec<String>) -> Result<(), Error> {
+    let json = serde_json::json!({ "Results" : results });
+    Ok(ws.send(Message::text(json.to_string()))?)
+}
+
+// usb2snes client that can connect to any address, SyncClient only knows the usb2snes port.
+// It sends the same queries as SyncClient
+pub struct MockClient {
+    ws : WebSocket<MaybeTlsStream<TcpStream>>
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+struct Reply {
+    Results : Vec<String>
+}
+
+impl MockClient {
+    pub fn connect(address : SocketAddr) -> Result<MockClient, Error> {
+        let (ws, _) = connect(format!("ws://{}", address))?;
+        Ok(MockClient {
+            ws
+        })
+    }
+    fn query(&mut self, opcode : &str, operands : Vec<String>) -> Result<(), Error> {
+        let json = serde_json::json!({ "Opcode" : opcode, "Space" : "SNES", "Flags" : [], "Operands" : operands });
+        Ok(self.ws.send(Message::text(json.to_string()))?)
+    }
+    fn results(&mut self) -> Result<Vec<String>, Error> {
+        loop {
+            if let Message::Text(text) = self.ws.read()? {
+                let reply : Reply = serde_json::from_str(&text).map_err(|e| Error::Io(e.into()))?;
+                return Ok(reply.Results);
+            }
+        }
+    }
+}
+
+impl MockClient {
+    fn get_address(&mut self, addresses : &[u32], sizes : &[usize]) -> Result<Vec<u8>, Error> {
+        let mut operands = vec![];
+        for (address, size) in addresses.iter().zip(sizes.iter()) {
+            operands.push(format!("{:x}", address));
+            operands.push(format!("{:x}", size));
+        }
+        self.query("GetAddress", operands)?;
+        let total : usize = sizes.iter().sum();
+        let mut data : Vec<u8> = vec![];
+        while data.len() < total {
+            if let Message::Binary(chunk) = self.ws.read()? {
+                data.extend(chunk);
+            }
+        }
+        Ok(data)
+    }
+}
+
+impl MemorySource for MockClient {
+    fn read(&mut self, address : u32, size : usize) -> Result<Vec<u8>, Error> {
+        self.get_address(&[address], &[size])
+    }
+    fn read_multi(&mut self, addresses : &[u32], sizes : &[usize]) -> Result<Vec<u8>, Error> {
+        let mut bytes : Vec<u8> = vec![];
+        for (addresses, sizes) in vget_requests(addresses, sizes) {
+            bytes.extend(self.get_address(&addresses, &sizes)?);
+        }
+        Ok(bytes)
+    }
+}
+
+impl Usb2SnesClient for MockClient {
+    fn list_devices(&mut self) -> Result<Vec<String>, Error> {
+        self.query("DeviceList", vec![])?;
+        self.results()
+    }
+    fn attach_device(&mut self, device : &str) -> Result<(), Error> {
+        self.query("Attach", vec![device.to_string()])
+    }
+    fn game(&mut self) -> Result<String, Error> {
+        self.query("Info", vec![])?;
+        let mut results = self.results()?;
+        if results.len() < 3 {
+            return Err(Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, "Info needs 3 results")));
+        }
+        Ok(results.remove(2))
+    }
+}
@@ -1,39 +1,48 @@
-use std::ops::RangeBounds;
-
-use sdl2::video::{Window, WindowContext};
-use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::render::{BlendMode, Canvas};
+use sdl2::surface::{Surface, SurfaceRef};
+use sdl2::pixels::PixelFormatEnum;
 use sdl2::ttf::{Sdl2TtfContext, Font};
 use sdl2::pixels::{Color};
 use sdl2::rect::{Rect, Point};
+use crate::data::{WHITE, BLACK};
 
 macro_rules! rect(
     ($x:expr, $y:expr, $w:expr, $h:expr) => (
         Rect::new($x as i32, $y as i32, $w as u32, $h as u32)
     )
 );
+// Everything is drawn off-screen on a surface, the window only shows it
 pub struct MyCanvas<'a> {
-    canvas : &'a mut Canvas<Window>,
-    ttf_context : &'a Sdl2TtfContext,
+    canvas : Canvas<Surface<'static>>,
     default_font : &'a Font<'a, 'a>,
-    game_font : Font<'a, 'a>
+    game_font : Font<'a, 'a>,
+    game_offset : Point
 }
-const GAME_X_OFFSET : i32 = 20;
-const GAME_Y_OFFSET : i32 = 60;
+pub const GAME_X_OFFSET : i32 = 20;
+pub const GAME_Y_OFFSET : i32 = 60;
 
 impl<'a> MyCanvas<'a> {
-    pub fn new(c : &'a mut Canvas<Window>, ttf : &'a Sdl2TtfContext, font : &'a Font) -> MyCanvas<'a> {
-        MyCanvas {
-            canvas : c,
-            ttf_context : ttf,
+    pub fn new(width : u32, height : u32, ttf : &'a Sdl2TtfContext, font : &'a Font) -> Result<MyCanvas<'a>, String> {
+        let surface = Surface::new(width, height, PixelFormatEnum::RGB888)?;
+        Ok(MyCanvas {
+            canvas : surface.into_canvas()?,
             default_font : font,
-            game_font  : ttf.load_font("FreeMonoBold.ttf", 11).unwrap()
-        }
+            game_font  : ttf.load_font("FreeMonoBold.ttf", 11)?,
+            game_offset : Point::new(GAME_X_OFFSET, GAME_Y_OFFSET)
+        })
+    }
+    // Where the game coordinates start on the canvas
+    pub fn set_game_offset(&mut self, x : i32, y : i32) {
+        self.game_offset = Point::new(x, y);
     }
     pub fn clear(&mut self) {
         self.canvas.clear();
     }
-    pub fn present(&mut self) {
-        self.canvas.present();
+    pub fn surface(&self) -> &SurfaceRef {
+        self.canvas.surface()
+    }
+    pub fn into_surface(self) -> Surface<'static> {
+        self.canvas.into_surface()
     }
     pub fn draw_text(&mut self, x : i32, y : i32, text : &str) -> Result<(), String> {
         let usb2snes_status_surface = self.default_font.render(text)
@@ -59,7 +68,7 @@ impl<'a> MyCanvas<'a> {
         .create_texture_from_surface(&usb2snes_status_surface)
         .map_err(|e| e.to_string())?;
         let dest_rect : Rect = rect!(
-            x + GAME_X_OFFSET, y + GAME_Y_OFFSET,
+            x + self.game_offset.x, y + self.game_offset.y,
             usb2snes_status_surface.rect().width(),
             usb2snes_status_surface.rect().height());
         self.canvas.copy(&texture, None, Some(dest_rect))?;
@@ -96,14 +105,91 @@ impl<'a> MyCanvas<'a> {
         self.canvas.set_draw_color(color);
     }
     pub fn draw_game_line(&mut self, x : i32, y : i32, x2 : i32, y2 : i32) ->Result<(), String> {
-        self.canvas.draw_line(Point::new(x + GAME_X_OFFSET, y + GAME_Y_OFFSET), Point::new(x2 + GAME_X_OFFSET, y2 + GAME_Y_OFFSET))
+        self.canvas.draw_line(Point::new(x + self.game_offset.x, y + self.game_offset.y), Point::new(x2 + self.game_offset.x, y2 + self.game_offset.y))
     }
 
     pub fn draw_game_box(&mut self, x : i32, y : i32, w : i32, h : i32, color : Color) ->Result<(), String> {
-        self.draw_box_cord(x + GAME_X_OFFSET, y + GAME_Y_OFFSET, w, h, color)
+        self.draw_box_cord(x + self.game_offset.x, y + self.game_offset.y, w, h, color)
     }
     pub fn draw_game_box_fill(&mut self, x : i32, y : i32, w : i32, h : i32, color : Color, color_fill : Color) -> Result<(), String> {
-        self.draw_box_cord_fill(x + GAME_X_OFFSET, y + GAME_Y_OFFSET, w, h, color, color_fill)
+        self.draw_box_cord_fill(x + self.game_offset.x, y + self.game_offset.y, w, h, color, color_fill)
+    }
+
+    // Box filled with a translucent color, the alpha of the color is used
+    pub fn draw_game_box_tint(&mut self, x : i32, y : i32, w : i32, h : i32, color : Color) -> Result<(), String> {
+        let old_color = self.canvas.draw_color();
+        let old_blend = self.canvas.blend_mode();
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(color);
+        self.canvas.fill_rect(rect!(x + self.game_offset.x, y + self.game_offset.y, w, h))?;
+        self.canvas.set_blend_mode(old_blend);
+        self.canvas.set_draw_color(old_color);
+        Ok(())
+    }
+
+    // Filled polygon, the fill is done one row at a time with the even-odd rule
+    pub fn draw_game_polygon(&mut self, points : &[(i32, i32)], color : Color, color_fill : Color) -> Result<(), String> {
+        if points.len() < 3 {
+            return Ok(());
+        }
+        let old_color = self.canvas.draw_color();
+        let top = points.iter().map(|p| p.1).min().unwrap();
+        let bottom = points.iter().map(|p| p.1).max().unwrap();
+        self.canvas.set_draw_color(color_fill);
+        for y in top..bottom {
+            // Crossings of the middle of the pixel row
+            let row = y as f32 + 0.5;
+            let mut crossings : Vec<i32> = vec![];
+            for i in 0..points.len() {
+                let (x1, y1) = points[i];
+                let (x2, y2) = points[(i + 1) % points.len()];
+                if (y1 as f32 <= row) != (y2 as f32 <= row) {
+                    let x = x1 as f32 + (row - y1 as f32) * (x2 - x1) as f32 / (y2 - y1) as f32;
+                    crossings.push(x.round() as i32);
+                }
+            }
+            crossings.sort();
+            for pair in crossings.chunks(2) {
+                if pair.len() == 2 && pair[1] > pair[0] {
+                    self.canvas.draw_line(Point::new(pair[0] + self.game_offset.x, y + self.game_offset.y), Point::new(pair[1] - 1 + self.game_offset.x, y + self.game_offset.y))?;
+                }
+            }
+        }
+        self.canvas.set_draw_color(color);
+        for i in 0..points.len() {
+            let (x1, y1) = points[i];
+            let (x2, y2) = points[(i + 1) % points.len()];
+            self.draw_game_line(x1, y1, x2, y2)?;
+        }
+        self.canvas.set_draw_color(old_color);
+        Ok(())
+    }
+
+    // Text box with a line per entry, kept inside the game area
+    pub fn draw_game_tooltip(&mut self, x : i32, y : i32, lines : &[String]) -> Result<(), String> {
+        let mut width : u32 = 0;
+        for line in lines {
+            width = width.max(self.game_font.size_of(line).map_err(|e| e.to_string())?.0);
+        }
+        let line_height = self.game_font.height();
+        let w = width as i32 + 6;
+        let h = lines.len() as i32 * line_height + 4;
+        let x = x.min(512 - w).max(0);
+        let y = if y + h > 448 { (y - h - 16).max(0) } else { y };
+        self.draw_game_box_fill(x, y, w, h, WHITE, BLACK)?;
+        for (i, line) in lines.iter().enumerate() {
+            self.draw_game_text(x + 3, y + 2 + i as i32 * line_height, line, WHITE)?;
+        }
+        Ok(())
+    }
+
+    // Draw a surface scaled in the game area
+    pub fn draw_game_surface(&mut self, surface : &SurfaceRef, x : i32, y : i32, w : i32, h : i32) -> Result<(), String> {
+        let texture_creator = self.canvas.texture_creator();
+        let texture = texture_creator
+        .create_texture_from_surface(surface)
+        .map_err(|e| e.to_string())?;
+        self.canvas.copy(&texture, None, Some(rect!(x + self.game_offset.x, y + self.game_offset.y, w, h)))
     }
 
     /*pub fn copy(&mut self, texture : &Texture<'_>, src : Option<Rect>, dst : Option<Rect>) -> Result<(), String> {
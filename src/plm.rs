@@ -0,0 +1,43 @@
+pub const PLM_SLOTS : usize = 40;
+pub const PLM_ARRAY_SIZE : usize = PLM_SLOTS * 2;
+
+// Post-load modification : the id is the PLM header pointer in bank $84,
+// block is where it is in the level data (twice the tile index)
+#[derive(Clone)]
+pub struct Plm {
+    pub slot : usize,
+    pub id : u16,
+    pub block : u16
+}
+
+impl Plm {
+    pub fn tile_index(&self) -> usize {
+        (self.block / 2) as usize
+    }
+}
+
+// Kind of PLM from the id ranges of the vanilla bank $84
+pub fn plm_kind(id : u16) -> &'static str {
+    match id {
+        0xB63B..=0xB6FF => "Scroll",
+        0xB703 => "Scroll",
+        0xC794..=0xC7FF => "Grey door",
+        0xC800..=0xC8FF => "Door",
+        0xEED7..=0xEF27 => "Item",
+        0xEF2B..=0xEF7B => "Chozo item",
+        0xEF7F..=0xEFCF => "Hidden item",
+        _ => "PLM"
+    }
+}
+
+fn word(bytes : &[u8], slot : usize) -> u16 {
+    bytes[slot * 2] as u16 + bytes[slot * 2 + 1] as u16 * 256
+}
+
+// Used slots of the PLM table, free slots have id 0
+pub fn parse_plms(ids : &[u8], blocks : &[u8]) -> Vec<Plm> {
+    (0..PLM_SLOTS)
+        .map(|slot| Plm { slot, id : word(ids, slot), block : word(blocks, slot) })
+        .filter(|plm| plm.id != 0)
+        .collect()
+}
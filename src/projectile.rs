@@ -0,0 +1,113 @@
+pub const PROJECTILE_SLOTS : usize = 10;
+// Every projectile field is an array of one word per slot
+pub const PROJECTILE_ARRAY_SIZE : usize = PROJECTILE_SLOTS * 2;
+
+// Samus projectile, slots 0-4 are beams and missiles and slots 5-9 bombs
+#[derive(Clone)]
+pub struct Projectile {
+    pub slot : usize,
+    pub x : i32,
+    pub y : i32,
+    pub radius_x : i32,
+    pub radius_y : i32,
+    pub projectile_type : u16
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ProjectileKind {
+    Beam,
+    Missile,
+    SuperMissile,
+    PowerBomb,
+    Bomb,
+    Other
+}
+
+impl Projectile {
+    pub fn kind(&self) -> ProjectileKind {
+        match self.projectile_type & 0x0F00 {
+            0x0000 => ProjectileKind::Beam,
+            0x0100 => ProjectileKind::Missile,
+            0x0200 => ProjectileKind::SuperMissile,
+            0x0300 => ProjectileKind::PowerBomb,
+            0x0500 => ProjectileKind::Bomb,
+            _ => ProjectileKind::Other
+        }
+    }
+    // Short text drawn on the hitbox, beams list their flags : charged, wave, ice, spazer and plasma
+    pub fn label(&self) -> String {
+        match self.kind() {
+            ProjectileKind::Beam => {
+                let mut label = String::new();
+                for (bit, letter) in [(0x10, 'C'), (0x01, 'W'), (0x02, 'I'), (0x04, 'S'), (0x08, 'P')] {
+                    if self.projectile_type & bit != 0 {
+                        label.push(letter);
+                    }
+                }
+                if label.is_empty() { String::from("B") } else { label }
+            },
+            ProjectileKind::Missile => String::from("M"),
+            ProjectileKind::SuperMissile => String::from("SM"),
+            ProjectileKind::PowerBomb => String::from("PB"),
+            ProjectileKind::Bomb => String::from("b"),
+            ProjectileKind::Other => format!("{:04X}", self.projectile_type)
+        }
+    }
+}
+
+fn word(bytes : &[u8], slot : usize) -> u16 {
+    bytes[slot * 2] as u16 + bytes[slot * 2 + 1] as u16 * 256
+}
+
+// Build the projectiles from the X, Y, X radius, Y radius and type arrays, free slots have no radius
+pub fn parse_projectiles(x : &[u8], y : &[u8], radius_x : &[u8], radius_y : &[u8], types : &[u8]) -> Vec<Projectile> {
+    let mut projectiles = vec![];
+    for slot in 0..PROJECTILE_SLOTS {
+        let projectile = Projectile {
+            slot,
+            x : word(x, slot) as i32,
+            y : word(y, slot) as i32,
+            radius_x : word(radius_x, slot) as i32,
+            radius_y : word(radius_y, slot) as i32,
+            projectile_type : word(types, slot)
+        };
+        if projectile.radius_x != 0 || projectile.radius_y != 0 {
+            projectiles.push(projectile);
+        }
+    }
+    projectiles
+}
+
+pub const ENEMY_PROJECTILE_SLOTS : usize = 18;
+pub const ENEMY_PROJECTILE_ARRAY_SIZE : usize = ENEMY_PROJECTILE_SLOTS * 2;
+
+// Enemy projectile, the id is its header pointer in bank $86
+#[derive(Clone)]
+pub struct EnemyProjectile {
+    pub slot : usize,
+    pub id : u16,
+    pub x : i32,
+    pub y : i32,
+    pub radius_x : i32,
+    pub radius_y : i32
+}
+
+// Build the enemy projectiles from the id, X, Y, X radius and Y radius arrays, free slots have id 0
+pub fn parse_enemy_projectiles(ids : &[u8], x : &[u8], y : &[u8], radius_x : &[u8], radius_y : &[u8]) -> Vec<EnemyProjectile> {
+    let mut projectiles = vec![];
+    for slot in 0..ENEMY_PROJECTILE_SLOTS {
+        let id = word(ids, slot);
+        if id == 0 {
+            continue;
+        }
+        projectiles.push(EnemyProjectile {
+            slot,
+            id,
+            x : word(x, slot) as i32,
+            y : word(y, slot) as i32,
+            radius_x : word(radius_x, slot) as i32,
+            radius_y : word(radius_y, slot) as i32
+        });
+    }
+    projectiles
+}
@@ -0,0 +1,273 @@
+use std::fs::File;
+use std::io::{Read, Write, Error, ErrorKind};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use crate::memorysource::{Error as SourceError, MemorySource, MemoryImage, WRAM_BASE, WRAM_SIZE};
+
+// Recording file : the magic then a gzip stream of one record per WRAM read made on the source
+// time in ms (u32), number of blocks (u16), address (u32) and size (u32) of each block
+// then the data of all blocks appended. Everything is little endian.
+// The ROM is not recorded, the replay reads it from --rom.
+const MAGIC : &[u8] = b"SMTVREC1";
+// Records written between two flushes of the compressed stream
+const FLUSH_RECORDS : u32 = 60;
+
+pub struct Recorder {
+    file : GzEncoder<File>,
+    start : Instant,
+    pending : u32
+}
+
+impl Recorder {
+    pub fn new(path : &str) -> Result<Recorder, Error> {
+        let mut file = File::create(path)?;
+        file.write_all(MAGIC)?;
+        Ok(Recorder {
+            file : GzEncoder::new(file, Compression::default()),
+            start : Instant::now(),
+            pending : 0
+        })
+    }
+    fn record(&mut self, addresses : &[u32], sizes : &[usize], data : &[u8]) -> Result<(), Error> {
+        let time = self.start.elapsed().as_millis() as u32;
+        self.file.write_all(&time.to_le_bytes())?;
+        self.file.write_all(&(addresses.len() as u16).to_le_bytes())?;
+        for (address, size) in addresses.iter().zip(sizes.iter()) {
+            self.file.write_all(&address.to_le_bytes())?;
+            self.file.write_all(&(*size as u32).to_le_bytes())?;
+        }
+        self.file.write_all(data)?;
+        // The viewer is closed by killing everything, at most the last second is lost
+        self.pending += 1;
+        if self.pending == FLUSH_RECORDS {
+            self.pending = 0;
+            self.file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.file.try_finish();
+    }
+}
+
+// Wrap a source to record everything read from it
+pub struct RecordingSource<'a, S : MemorySource> {
+    source : &'a mut S,
+    recorder : &'a mut Recorder
+}
+
+impl<'a, S : MemorySource> RecordingSource<'a, S> {
+    pub fn new(source : &'a mut S, recorder : &'a mut Recorder) -> RecordingSource<'a, S> {
+        RecordingSource {
+            source,
+            recorder
+        }
+    }
+}
+
+impl<'a, S : MemorySource> MemorySource for RecordingSource<'a, S> {
+    fn read(&mut self, address : u32, size : usize) -> Result<Vec<u8>, SourceError> {
+        let data = self.source.read(address, size)?;
+        if address >= WRAM_BASE {
+            self.recorder.record(&[address], &[size], &data)?;
+        }
+        Ok(data)
+    }
+    fn read_multi(&mut self, addresses : &[u32], sizes : &[usize]) -> Result<Vec<u8>, SourceError> {
+        let data = self.source.read_multi(addresses, sizes)?;
+        self.recorder.record(addresses, sizes, &data)?;
+        Ok(data)
+    }
+    fn has_rom(&self) -> bool {
+        self.source.has_rom()
+    }
+}
+
+struct Record {
+    time : u32,
+    blocks : Vec<(u32, usize)>,
+    data : Vec<u8>
+}
+
+// Shared between the replay and the render loop
+#[derive(Default)]
+pub struct ReplayControl {
+    pub paused : bool,
+    pub step : i32,
+    pub seek : i32,
+    pub position : u32,
+    pub length : u32
+}
+
+impl ReplayControl {
+    pub fn new() -> ReplayControl {
+        ReplayControl::default()
+    }
+}
+
+// Play a recording back. Each read_multi is a poll and moves to the next frame,
+// the memory image holds everything recorded up to the next poll.
+pub struct ReplaySource {
+    records : Vec<Record>,
+    frames : Vec<usize>,
+    frame : usize,
+    applied : usize,
+    image : MemoryImage,
+    rom : Vec<u8>,
+    control : Arc<Mutex<ReplayControl>>,
+    base_instant : Instant,
+    base_time : u32,
+    started : bool
+}
+
+impl ReplaySource {
+    // The ROM is the one given with --rom, empty without it
+    pub fn load(path : &str, rom : Vec<u8>, control : Arc<Mutex<ReplayControl>>) -> Result<ReplaySource, Error> {
+        let mut file = File::open(path)?;
+        let mut magic = [0u8; 8];
+        if file.read_exact(&mut magic).is_err() || magic != MAGIC {
+            return Err(Error::new(ErrorKind::InvalidData, "Not a SMTileViewer recording"));
+        }
+        // A recording stopped by killing the viewer has no gzip trailer, keep what could be read
+        let mut content : Vec<u8> = vec![];
+        if let Err(err) = GzDecoder::new(file).read_to_end(&mut content) {
+            if err.kind() != ErrorKind::UnexpectedEof {
+                return Err(err);
+            }
+        }
+        let mut records : Vec<Record> = vec![];
+        let mut frames : Vec<usize> = vec![];
+        let mut pos = 0;
+        let u32_at = |p : usize| u32::from_le_bytes([content[p], content[p + 1], content[p + 2], content[p + 3]]);
+        while pos + 6 <= content.len() {
+            let time = u32_at(pos);
+            let count = u16::from_le_bytes([content[pos + 4], content[pos + 5]]) as usize;
+            pos += 6;
+            if pos + count * 8 > content.len() {
+                break;
+            }
+            let mut blocks : Vec<(u32, usize)> = vec![];
+            for i in 0..count {
+                blocks.push((u32_at(pos + i * 8), u32_at(pos + i * 8 + 4) as usize));
+            }
+            pos += count * 8;
+            let size : usize = blocks.iter().map(|b| b.1).sum();
+            // A record cut by the end of the recording is dropped
+            if pos + size > content.len() {
+                break;
+            }
+            if count > 1 {
+                frames.push(records.len());
+            }
+            records.push(Record { time, blocks, data : content[pos..pos + size].to_vec() });
+            pos += size;
+        }
+        if frames.is_empty() {
+            return Err(Error::new(ErrorKind::InvalidData, "The recording is empty"));
+        }
+        control.lock().unwrap().length = records[*frames.last().unwrap()].time - records[frames[0]].time;
+        Ok(ReplaySource {
+            records,
+            frames,
+            frame : 0,
+            applied : 0,
+            image : MemoryImage::new(vec![0; WRAM_SIZE], rom.clone()),
+            rom,
+            control,
+            base_instant : Instant::now(),
+            base_time : 0,
+            started : false
+        })
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    fn frame_time(&self, frame : usize) -> u32 {
+        self.records[self.frames[frame]].time
+    }
+
+    fn write_image(&mut self, address : u32, data : &[u8]) {
+        let offset = (address - WRAM_BASE) as usize;
+        self.image.wram[offset..offset + data.len()].copy_from_slice(data);
+    }
+
+    // Make the image match the state right before the poll following this frame
+    fn go_to_frame(&mut self, frame : usize) {
+        if frame < self.frame || !self.started {
+            self.image = MemoryImage::new(vec![0; WRAM_SIZE], self.rom.clone());
+            self.applied = 0;
+        }
+        let end = if frame + 1 < self.frames.len() { self.frames[frame + 1] } else { self.records.len() };
+        while self.applied < end {
+            let mut offset = 0;
+            for i in 0..self.records[self.applied].blocks.len() {
+                let (address, size) = self.records[self.applied].blocks[i];
+                let data = self.records[self.applied].data[offset..offset + size].to_vec();
+                self.write_image(address, &data);
+                offset += size;
+            }
+            self.applied += 1;
+        }
+        self.frame = frame;
+        self.started = true;
+    }
+
+    fn frame_at(&self, time : u32) -> usize {
+        self.frames.iter().rposition(|f| self.records[*f].time <= time).unwrap_or_default()
+    }
+
+    fn next_frame(&mut self) -> usize {
+        let last = self.frames.len() - 1;
+        let mut control = self.control.lock().unwrap();
+        let mut frame = self.frame;
+        let mut moved = true;
+        if !self.started {
+            frame = 0;
+        } else if control.seek != 0 {
+            let time = (self.frame_time(self.frame) as i64 + control.seek as i64).max(0) as u32;
+            frame = self.frame_at(time);
+            control.seek = 0;
+        } else if control.step != 0 {
+            frame = (frame as i64 + control.step as i64).clamp(0, last as i64) as usize;
+            control.step = 0;
+        } else if !control.paused {
+            let time = self.base_time + self.base_instant.elapsed().as_millis() as u32;
+            frame = self.frame_at(time).max(frame);
+            moved = false;
+            if frame == last {
+                control.paused = true;
+            }
+        }
+        // Restart the clock from the shown frame when the user moved in the recording
+        if moved || control.paused {
+            self.base_instant = Instant::now();
+            self.base_time = self.frame_time(frame);
+        }
+        control.position = self.frame_time(frame) - self.frame_time(0);
+        frame
+    }
+}
+
+impl MemorySource for ReplaySource {
+    fn read(&mut self, address : u32, size : usize) -> Result<Vec<u8>, SourceError> {
+        self.image.read(address, size)
+    }
+    fn read_multi(&mut self, addresses : &[u32], sizes : &[usize]) -> Result<Vec<u8>, SourceError> {
+        let frame = self.next_frame();
+        if frame != self.frame || !self.started {
+            self.go_to_frame(frame);
+        }
+        self.image.read_multi(addresses, sizes)
+    }
+    fn has_rom(&self) -> bool {
+        self.image.has_rom()
+    }
+}
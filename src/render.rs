@@ -0,0 +1,527 @@
+use std::fs;
+use sdl2::image::SaveSurface;
+use sdl2::surface::{Surface, SurfaceRef};
+use sdl2::ttf::{Sdl2TtfContext, Font};
+use crate::data::{*};
+use crate::memorysource::MemoryImage;
+use crate::mycanvas::MyCanvas;
+use crate::rom::load_rom_file;
+use crate::room;
+use crate::enemy;
+use crate::plm;
+use crate::samus;
+use crate::projectile::ProjectileKind;
+use sdl2::pixels::Color;
+use sdl2::rect::Point;
+use crate::savestate;
+use crate::wsthread;
+
+pub const CANVAS_WIDTH : u32 = 550;
+pub const CANVAS_HEIGHT : u32 = 600;
+// Extra status lines go under the game area
+pub const STATUS_Y : i32 = 510;
+pub const STATUS_LINE_HEIGHT : i32 = 16;
+
+// Draw the tiles of the game area, camera is the room position of its top left corner
+pub fn draw_game(canvas : &mut MyCanvas, data : &SharedData) -> Result<(), String> {
+    let camera = data.camera;
+    let radius = data.radius;
+    let mut game_tile_data : GameTileData = GameTileData { tile_x: 0, tile_y: 0, bts: 0, bts_value: 0, clip: 0, clip_value: 0, door_stuff : data.door_stuff, bts_byte : 0, slope_columns : None };
+    if !data.map_data.is_empty() {
+        // One more row and column for when the camera isn't aligned on the tiles
+        for y in 0..29 {
+            for x in 0..33 {
+                let room_x = camera.x.div_euclid(16) * 16 + x * 16;
+                let room_y = camera.y.div_euclid(16) * 16 + y * 16;
+                if let Some(a) = room_tile(data, room_x, room_y) {
+                    draw_tile(canvas, data, a, room_x - camera.x, room_y - camera.y, &mut game_tile_data)?;
+                    if tile_changed(data, a) {
+                        canvas.draw_game_box(room_x - camera.x, room_y - camera.y, 16, 16, MAGENTA)?;
+                    }
+                }
+            }
+        }
+    }
+    draw_plms(canvas, data)?;
+    draw_enemies(canvas, data)?;
+    draw_projectiles(canvas, data)?;
+    draw_enemy_projectiles(canvas, data)?;
+    draw_trail(canvas, data)?;
+    let samus = data.samus_pos;
+    let samus_color = movement_type_color(data.movement.movement_type);
+    canvas.draw_game_box_fill(samus.x - camera.x - radius.x, samus.y - camera.y - radius.y, radius.x * 2, radius.y * 2, samus_color, samus_color)?;
+    // What the game shows on screen
+    canvas.draw_game_box(data.layer1.x - camera.x, data.layer1.y - camera.y, 256, 224, GREY)?;
+    Ok(())
+}
+
+// Samus hitbox of the last frames, fading with age
+pub fn draw_trail(canvas : &mut MyCanvas, data : &SharedData) -> Result<(), String> {
+    let camera = data.camera;
+    let length = data.trail.len().max(1) as f32;
+    for (age, (position, radius)) in data.trail.iter().rev().enumerate().skip(1) {
+        let fade = 1.0 - age as f32 / length;
+        let color = Color::RGB(0, (200.0 * fade) as u8 + 30, (200.0 * fade) as u8 + 30);
+        canvas.draw_game_box(position.x - camera.x - radius.x, position.y - camera.y - radius.y, (radius.x * 2).max(1), (radius.y * 2).max(1), color)?;
+    }
+    Ok(())
+}
+
+// The 256x224 game screen is centered in the 512x448 game area
+pub fn game_camera(layer1 : Point) -> Point {
+    Point::new(layer1.x - 128, layer1.y - 112)
+}
+
+// Put Samus in the middle of the game area instead of following the game camera
+pub fn samus_camera(data : &SharedData) -> Point {
+    Point::new(data.samus_pos.x - 256, data.samus_pos.y - 224)
+}
+
+// PLMs are drawn on their tile with their id, they often change how the tile behaves
+pub fn draw_plms(canvas : &mut MyCanvas, data : &SharedData) -> Result<(), String> {
+    let width = (data.width as usize).max(1);
+    for plm in data.plms.iter() {
+        let x = (plm.tile_index() % width) as i32 * 16 - data.camera.x;
+        let y = (plm.tile_index() / width) as i32 * 16 - data.camera.y;
+        if x < -16 || y < -16 || x >= 512 || y >= 448 {
+            continue;
+        }
+        canvas.draw_game_box(x + 2, y + 2, 12, 12, WHITE)?;
+        canvas.draw_game_text(x, y + 6, format!("{:04X}", plm.id).as_str(), WHITE)?;
+    }
+    Ok(())
+}
+
+fn projectile_color(kind : ProjectileKind) -> Color {
+    match kind {
+        ProjectileKind::Beam => YELLOW,
+        ProjectileKind::Missile | ProjectileKind::SuperMissile => GREEN,
+        ProjectileKind::Bomb | ProjectileKind::PowerBomb => PINK,
+        ProjectileKind::Other => WHITE
+    }
+}
+
+// Hitbox of Samus projectiles with a short label of their type
+pub fn draw_projectiles(canvas : &mut MyCanvas, data : &SharedData) -> Result<(), String> {
+    let camera = data.camera;
+    for projectile in data.projectiles.iter() {
+        let x = projectile.x - camera.x;
+        let y = projectile.y - camera.y;
+        if x + projectile.radius_x < 0 || y + projectile.radius_y < 0 || x - projectile.radius_x >= 512 || y - projectile.radius_y >= 448 {
+            continue;
+        }
+        let color = projectile_color(projectile.kind());
+        canvas.draw_game_box(x - projectile.radius_x, y - projectile.radius_y, (projectile.radius_x * 2).max(1), (projectile.radius_y * 2).max(1), color)?;
+        canvas.draw_game_text(x + projectile.radius_x + 1, y - projectile.radius_y - 4, projectile.label().as_str(), color)?;
+    }
+    Ok(())
+}
+
+// Enemy shots, with the id of their header
+pub fn draw_enemy_projectiles(canvas : &mut MyCanvas, data : &SharedData) -> Result<(), String> {
+    let camera = data.camera;
+    for projectile in data.enemy_projectiles.iter() {
+        let x = projectile.x - camera.x;
+        let y = projectile.y - camera.y;
+        if x + projectile.radius_x < 0 || y + projectile.radius_y < 0 || x - projectile.radius_x >= 512 || y - projectile.radius_y >= 448 {
+            continue;
+        }
+        canvas.draw_game_box(x - projectile.radius_x, y - projectile.radius_y, (projectile.radius_x * 2).max(1), (projectile.radius_y * 2).max(1), RED)?;
+        canvas.draw_game_text(x + projectile.radius_x + 1, y - projectile.radius_y - 4, format!("{:04X}", projectile.id).as_str(), RED)?;
+    }
+    Ok(())
+}
+
+// Hitbox of every enemy with its name and health, intangible enemies are greyed
+pub fn draw_enemies(canvas : &mut MyCanvas, data : &SharedData) -> Result<(), String> {
+    let camera = data.camera;
+    for enemy in data.enemies.iter() {
+        let x = enemy.x - camera.x;
+        let y = enemy.y - camera.y;
+        if x + enemy.radius_x < 0 || y + enemy.radius_y < 0 || x - enemy.radius_x >= 512 || y - enemy.radius_y >= 448 {
+            continue;
+        }
+        let color = if enemy.intangible() { GREY } else { ORANGE };
+        canvas.draw_game_box(x - enemy.radius_x, y - enemy.radius_y, (enemy.radius_x * 2).max(1), (enemy.radius_y * 2).max(1), color)?;
+        let name = enemy::enemy_name(&data.rom_data, enemy.id);
+        let invisible = if enemy.invisible() { " (invisible)" } else { "" };
+        canvas.draw_game_text(x - enemy.radius_x, y - enemy.radius_y - 10, format!("{} {}{}", name, enemy.health, invisible).as_str(), color)?;
+    }
+    Ok(())
+}
+
+// Draw the tile at index a in the level data, extensions are drawn like their parent tile
+fn draw_tile(canvas : &mut MyCanvas, data : &SharedData, a : usize, tile_x : i32, tile_y : i32, game_tile_data : &mut GameTileData) -> Result<(), String> {
+    let map_infos = &data.map_data;
+    let width = data.width as usize;
+    let parent = extension_parent(map_infos, width, a);
+    let source = parent.unwrap_or(a);
+    let bts : usize = (0x6402 + source) % 0x10000;
+    let bts_value = map_infos[bts];
+    let clip : usize = (0x0002 + source * 2) % 0x10000;
+    let clip_value : u16 = map_infos[clip + 1] as u16  * 256 + map_infos[clip] as u16;
+    let outline_index : u8 = (clip_value >> 12) as u8;
+    game_tile_data.tile_x = tile_x;
+    game_tile_data.tile_y = tile_y;
+    game_tile_data.bts = bts;
+    game_tile_data.clip = clip;
+    game_tile_data.bts_value = bts_value;
+    game_tile_data.slope_columns = if outline_index == 1 { slope_columns(&data.rom_data, bts_value) } else { None };
+    if OUTLINES.contains_key(&outline_index) {
+        OUTLINES[&outline_index](canvas, game_tile_data)?;
+    } else {
+        canvas.draw_game_box_fill(tile_x, tile_y, 16, 16, DARK_GREEN, GREY)?;
+        canvas.draw_game_text(tile_x + 3, tile_y - 1, format!("{:02x}", outline_index).as_str(), DARK_GREEN)?;
+    }
+    if let Some(parent) = parent {
+        let width = width.max(1) as i32;
+        let dx = (parent as i32 % width - a as i32 % width).signum();
+        let dy = (parent as i32 / width - a as i32 / width).signum();
+        draw_extension_arrow(canvas, tile_x, tile_y, dx, dy)?;
+    }
+    Ok(())
+}
+
+// Follow the signed BTS offset of horizontal (5) and vertical (D) extensions until a real block.
+// None if the tile isn't an extension or the chain goes nowhere
+pub fn extension_parent(map_infos : &[u8], width : usize, a : usize) -> Option<usize> {
+    let mut current = a;
+    for _ in 0..16 {
+        let clip = (0x0002 + current * 2) % 0x10000;
+        let block_type = map_infos[clip + 1] >> 4;
+        let offset = map_infos[(0x6402 + current) % 0x10000] as i8 as i32;
+        let next = match block_type {
+            0x5 => current as i32 + offset,
+            0xD => current as i32 + offset * width as i32,
+            _ => return if current == a { None } else { Some(current) }
+        };
+        // Level data holds at most 0x3200 tiles
+        if offset == 0 || !(0..0x3200).contains(&next) {
+            return None;
+        }
+        current = next as usize;
+    }
+    None
+}
+
+// Small arrow in the corner of an extension tile, pointing to its parent
+fn draw_extension_arrow(canvas : &mut MyCanvas, tile_x : i32, tile_y : i32, dx : i32, dy : i32) -> Result<(), String> {
+    let x = tile_x + 11;
+    let y = tile_y + 11;
+    canvas.set_draw_color(YELLOW);
+    canvas.draw_game_line(x - dx * 3, y - dy * 3, x + dx * 3, y + dy * 3)?;
+    if dx != 0 {
+        canvas.draw_game_line(x + dx * 3, y, x + dx, y - 2)?;
+        canvas.draw_game_line(x + dx * 3, y, x + dx, y + 2)?;
+    }
+    if dy != 0 {
+        canvas.draw_game_line(x, y + dy * 3, x - 2, y + dy)?;
+        canvas.draw_game_line(x, y + dy * 3, x + 2, y + dy)?;
+    }
+    canvas.set_draw_color(BLACK);
+    Ok(())
+}
+
+// Index in the level data of the tile at this position of the room, None outside of the room
+pub fn room_tile(data : &SharedData, room_x : i32, room_y : i32) -> Option<usize> {
+    let width = data.width as i32;
+    if data.map_data.is_empty() || width == 0 {
+        return None;
+    }
+    // Level data holds at most 0x3200 tiles, use that if we don't know the height
+    let height = if data.height != 0 { data.height as i32 } else { 0x3200 / width };
+    if room_x < 0 || room_y < 0 || room_x >= width * 16 || room_y >= height * 16 {
+        return None;
+    }
+    Some(((room_y / 16) * width + room_x / 16) as usize)
+}
+
+// Index in the level data of the tile at this position of the game area
+pub fn tile_at(data : &SharedData, x : i32, y : i32) -> Option<usize> {
+    if x < 0 || y < 0 || x >= 512 || y >= 448 {
+        return None;
+    }
+    room_tile(data, data.camera.x + x, data.camera.y + y)
+}
+
+pub fn tile_clip(data : &SharedData, a : usize) -> u16 {
+    let clip : usize = (0x0002 + a * 2) % 0x10000;
+    data.map_data[clip + 1] as u16 * 256 + data.map_data[clip] as u16
+}
+
+pub fn tile_bts(data : &SharedData, a : usize) -> u8 {
+    data.map_data[(0x6402 + a) % 0x10000]
+}
+
+// The block was broken or came back since we entered the room
+pub fn tile_changed(data : &SharedData, a : usize) -> bool {
+    if data.room_map_data.len() != data.map_data.len() {
+        return false;
+    }
+    let clip = (0x0002 + a * 2) % 0x10000;
+    let bts = (0x6402 + a) % 0x10000;
+    data.map_data[clip] != data.room_map_data[clip] || data.map_data[clip + 1] != data.room_map_data[clip + 1] || data.map_data[bts] != data.room_map_data[bts]
+}
+
+// Everything about the tile under the mouse, and where it goes for doors
+pub fn draw_tile_tooltip(canvas : &mut MyCanvas, data : &SharedData, x : i32, y : i32) -> Result<(), String> {
+    match tile_at(data, x, y) {
+        Some(a) => canvas.draw_game_tooltip(x + 12, y + 12, &tile_tooltip_lines(data, a)),
+        None => Ok(())
+    }
+}
+
+// Text of the tooltip of tile a : the raw clip and BTS, then what they mean
+pub fn tile_tooltip_lines(data : &SharedData, a : usize) -> Vec<String> {
+    let clip_value = tile_clip(data, a);
+    let bts_value = tile_bts(data, a);
+    let block_type = (clip_value >> 12) as u8;
+    let width = (data.width as usize).max(1);
+    let mut lines : Vec<String> = vec![];
+    lines.push(format!("Tile {},{} - index ${:04X}", a % width, a / width, a));
+    lines.push(format!("{} - clip ${:04X} BTS ${:02X}", block_type_name(block_type), clip_value, bts_value));
+    let description = bts_description(block_type, bts_value);
+    // Nothing more to say than the raw value for most types
+    if description != format!("BTS ${:02X}", bts_value) {
+        lines.push(description);
+    }
+    if block_type == 1 {
+        if let Some(columns) = slope_columns(&data.rom_data, bts_value) {
+            lines.push(format!("Heights {}", columns.iter().map(|h| h.to_string()).collect::<Vec<String>>().join(" ")));
+        }
+    }
+    lines.push(format!("Clip $7F:{:04X} BTS $7F:{:04X}", (0x0002 + a * 2) % 0x10000, (0x6402 + a) % 0x10000));
+    if tile_changed(data, a) {
+        let clip = (0x0002 + a * 2) % 0x10000;
+        lines.push(format!("Changed, was clip ${:04X} BTS ${:02X}", data.room_map_data[clip + 1] as u16 * 256 + data.room_map_data[clip] as u16, data.room_map_data[(0x6402 + a) % 0x10000]));
+    }
+    if block_type == 0x5 || block_type == 0xD {
+        match extension_parent(&data.map_data, width, a) {
+            Some(parent) => lines.push(format!("Extends tile {},{} : {}", parent % width, parent / width, block_type_name((tile_clip(data, parent) >> 12) as u8))),
+            None => lines.push(String::from("Extension without parent"))
+        }
+    }
+    for plm in data.plms.iter().filter(|plm| plm.tile_index() == a) {
+        lines.push(format!("{} PLM ${:04X} (slot {})", plm::plm_kind(plm.id), plm.id, plm.slot));
+    }
+    if block_type == 9 {
+        let index = bts_value as usize;
+        match data.room.as_ref().and_then(|room| room.doors.get(index)) {
+            Some(door) => lines.extend(room::door_description(index, door)),
+            None => lines.push(String::from("No door data"))
+        }
+    }
+    lines
+}
+
+// Draw every tile of the current room, the canvas must be width * 16 x height * 16
+pub fn draw_room(canvas : &mut MyCanvas, data : &SharedData) -> Result<(), String> {
+    let mut game_tile_data : GameTileData = GameTileData { tile_x: 0, tile_y: 0, bts: 0, bts_value: 0, clip: 0, clip_value: 0, door_stuff : data.door_stuff, bts_byte : 0, slope_columns : None };
+    if data.map_data.is_empty() {
+        return Ok(());
+    }
+    for y in 0..data.height as i32 {
+        for x in 0..data.width as i32 {
+            let a = (y * data.width as i32 + x) as usize;
+            draw_tile(canvas, data, a, x * 16, y * 16, &mut game_tile_data)?;
+            if tile_changed(data, a) {
+                canvas.draw_game_box(x * 16, y * 16, 16, 16, MAGENTA)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn render_room(ttf_context : &Sdl2TtfContext, font : &Font, data : &SharedData) -> Result<Surface<'static>, String> {
+    let width = (data.width as u32).max(1) * 16;
+    let height = (data.height as u32).max(1) * 16;
+    let mut canvas = MyCanvas::new(width, height, ttf_context, font)?;
+    canvas.set_game_offset(0, 0);
+    canvas.clear();
+    draw_room(&mut canvas, data)?;
+    Ok(canvas.into_surface())
+}
+
+// Text version of the room : one line per row of tiles, each tile is clip word:BTS
+pub fn room_dump(data : &SharedData) -> String {
+    let mut dump = format!("# {} x {} tiles - clip:BTS\n", data.width, data.height);
+    if data.map_data.is_empty() {
+        return dump;
+    }
+    for y in 0..data.height as usize {
+        let mut line : Vec<String> = vec![];
+        for x in 0..data.width as usize {
+            let a = y * data.width as usize + x;
+            let clip = (0x0002 + a * 2) % 0x10000;
+            let clip_value = data.map_data[clip + 1] as u16 * 256 + data.map_data[clip] as u16;
+            line.push(format!("{:04X}:{:02X}", clip_value, data.map_data[(0x6402 + a) % 0x10000]));
+        }
+        dump.push_str(&line.join(" "));
+        dump.push('\n');
+    }
+    dump
+}
+
+// Save the whole room as a PNG and optionally as text
+pub fn export_room(ttf_context : &Sdl2TtfContext, font : &Font, data : &SharedData, png_file : &str, dump_file : Option<&str>) -> Result<(), String> {
+    if data.map_data.is_empty() {
+        return Err(String::from("No level data to export"));
+    }
+    render_room(ttf_context, font, data)?.save(png_file)?;
+    if let Some(dump_file) = dump_file {
+        fs::write(dump_file, room_dump(data)).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+// Scale of the room view so the whole room fits in the game area
+pub fn room_view_scale(room : &SurfaceRef) -> f32 {
+    (512.0 / room.width() as f32).min(448.0 / room.height() as f32).min(1.0)
+}
+
+// Show the whole room scaled down in the game area, with Samus on it
+pub fn draw_room_view(canvas : &mut MyCanvas, room : &SurfaceRef, data : &SharedData) -> Result<(), String> {
+    let room_width = room.width() as f32;
+    let room_height = room.height() as f32;
+    let scale = room_view_scale(room);
+    canvas.draw_game_surface(room, 0, 0, (room_width * scale) as i32, (room_height * scale) as i32)?;
+    let samus = data.samus_pos;
+    let radius = data.radius;
+    let samus_color = movement_type_color(data.movement.movement_type);
+    canvas.draw_game_box_fill(((samus.x - radius.x) as f32 * scale) as i32, ((samus.y - radius.y) as f32 * scale) as i32,
+        ((radius.x * 2) as f32 * scale).max(2.0) as i32, ((radius.y * 2) as f32 * scale).max(2.0) as i32, samus_color, samus_color)?;
+    for enemy in data.enemies.iter() {
+        canvas.draw_game_box(((enemy.x - enemy.radius_x) as f32 * scale) as i32, ((enemy.y - enemy.radius_y) as f32 * scale) as i32,
+            ((enemy.radius_x * 2) as f32 * scale).max(2.0) as i32, ((enemy.radius_y * 2) as f32 * scale).max(2.0) as i32, if enemy.intangible() { GREY } else { ORANGE })?;
+    }
+    let width = (data.width as usize).max(1);
+    for plm in data.plms.iter() {
+        canvas.draw_game_box((((plm.tile_index() % width) * 16) as f32 * scale) as i32, (((plm.tile_index() / width) * 16) as f32 * scale) as i32,
+            (16.0 * scale).max(2.0) as i32, (16.0 * scale).max(2.0) as i32, WHITE)?;
+    }
+    for projectile in data.enemy_projectiles.iter() {
+        canvas.draw_game_box(((projectile.x - projectile.radius_x) as f32 * scale) as i32, ((projectile.y - projectile.radius_y) as f32 * scale) as i32,
+            ((projectile.radius_x * 2) as f32 * scale).max(2.0) as i32, ((projectile.radius_y * 2) as f32 * scale).max(2.0) as i32, RED)?;
+    }
+    for projectile in data.projectiles.iter() {
+        canvas.draw_game_box(((projectile.x - projectile.radius_x) as f32 * scale) as i32, ((projectile.y - projectile.radius_y) as f32 * scale) as i32,
+            ((projectile.radius_x * 2) as f32 * scale).max(2.0) as i32, ((projectile.radius_y * 2) as f32 * scale).max(2.0) as i32, projectile_color(projectile.kind()))?;
+    }
+    Ok(())
+}
+
+fn scroll_color(scroll : u8) -> Color {
+    match scroll {
+        0 => SCROLL_RED,
+        1 => SCROLL_BLUE,
+        _ => SCROLL_GREEN
+    }
+}
+
+// Scroll of a screen of the room, the table has one byte per screen row by row
+pub fn screen_scroll(data : &SharedData, screen_x : usize, screen_y : usize) -> Option<u8> {
+    let screens_x = (data.width as usize / 16).max(1);
+    if screen_x >= screens_x {
+        return None;
+    }
+    data.scrolls.get(screen_y * screens_x + screen_x).copied()
+}
+
+// Tint every screen of the room with its scroll and draw the game camera.
+// The room pixel (x, y) is drawn at (origin_x + x * scale, origin_y + y * scale)
+pub fn draw_scrolls(canvas : &mut MyCanvas, data : &SharedData, origin_x : i32, origin_y : i32, scale : f32) -> Result<(), String> {
+    let screens_x = (data.width as usize / 16).max(1);
+    let screens_y = (data.height as usize / 16).max(1);
+    let screen_size = (256.0 * scale) as i32;
+    for screen_y in 0..screens_y {
+        for screen_x in 0..screens_x {
+            let scroll = match screen_scroll(data, screen_x, screen_y) {
+                Some(scroll) => scroll,
+                None => continue
+            };
+            let x = origin_x + (screen_x as f32 * 256.0 * scale) as i32;
+            let y = origin_y + (screen_y as f32 * 256.0 * scale) as i32;
+            if x + screen_size < 0 || y + screen_size < 0 || x >= 512 || y >= 448 {
+                continue;
+            }
+            canvas.draw_game_box_tint(x, y, screen_size, screen_size, scroll_color(scroll))?;
+            canvas.draw_game_box(x, y, screen_size, screen_size, scroll_color(scroll))?;
+            canvas.draw_game_text(x.max(0) + 2, y.max(0), format!("{:02X}", scroll).as_str(), WHITE)?;
+        }
+    }
+    canvas.draw_game_box(origin_x + (data.layer1.x as f32 * scale) as i32, origin_y + (data.layer1.y as f32 * scale) as i32,
+        (256.0 * scale) as i32, (224.0 * scale) as i32, WHITE)?;
+    Ok(())
+}
+
+// Hide the tiles drawn outside of the game area
+pub fn draw_borders(canvas : &mut MyCanvas) -> Result<(), String> {
+    canvas.draw_box_cord_fill(0, 50, 18, 600, BLACK, BLACK)?;
+    canvas.draw_box_cord_fill(0, 40, 580, 28, BLACK, BLACK)?;
+    canvas.draw_box_cord_fill(518, 50, 18, 600, BLACK, BLACK)?;
+    canvas.draw_box_cord_fill(0, 495, 580, 28, BLACK, BLACK)?;
+    Ok(())
+}
+
+fn load_frame(wram_file : &str, rom_file : Option<&str>) -> Result<SharedData, String> {
+    let wram = savestate::load_savestate_wram(wram_file).map_err(|e| format!("Can't load {} : {}", wram_file, e))?;
+    let rom = match rom_file {
+        Some(path) => load_rom_file(path).map_err(|e| format!("Can't load {} : {}", path, e))?,
+        None => vec![]
+    };
+    let mut source = MemoryImage::new(wram, rom);
+    let mut frame = SharedData::new();
+    wsthread::actually_getting_data(&mut source, &mut frame).map_err(|e| e.to_string())?;
+    if frame.map_data.is_empty() {
+        return Err(format!("No level data in {}, the game must be in a room", wram_file));
+    }
+    Ok(frame)
+}
+
+// Informations shown under the game area, the camera is the game one or the one centered on Samus
+pub fn status_lines(data : &SharedData, samus_view : bool) -> Vec<String> {
+    let mut lines : Vec<String> = vec![];
+    lines.push(format!("Samus coord : {},{} Camera : {} , {} ({}) - Width : {}", data.samus_pos.x, data.samus_pos.y, data.camera.x, data.camera.y,
+        if samus_view { "Samus" } else { "game" }, data.width));
+    if let Some(room) = &data.room {
+        lines.push(format!("Room : {}", room::room_description(room)));
+    }
+    let movement = &data.movement;
+    lines.push(format!("Samus X {} Y {} - Pose ${:02X} {} - {} facing {}", samus::format_subpixel(data.samus_pos.x, movement.subpixel_x),
+        samus::format_subpixel(data.samus_pos.y, movement.subpixel_y), movement.pose, pose_name(movement.pose), movement_type_name(movement.movement_type),
+        samus::direction_name(movement.direction)));
+    lines.push(format!("Speed X {} Momentum {} - Speed Y {}", samus::format_subpixel(movement.speed_x as i32, movement.subspeed_x),
+        samus::format_subpixel(movement.momentum_x as i32, movement.submomentum_x), samus::format_subpixel(movement.speed_y as i32, movement.subspeed_y)));
+    if let Some(frame_counter) = data.frame_counter {
+        lines.push(format!("Frame {} - {} frames missed by the polling - Game state ${:02X} {:?}", frame_counter, data.dropped_frames, data.game_state, data.game_phase));
+    }
+    lines
+}
+
+pub fn draw_status(canvas : &mut MyCanvas, lines : &[String]) -> Result<(), String> {
+    for (i, line) in lines.iter().enumerate() {
+        canvas.draw_text(0, STATUS_Y + i as i32 * STATUS_LINE_HEIGHT, line)?;
+    }
+    Ok(())
+}
+
+// Headless rendering of a WRAM dump (or savestate) to a PNG file
+pub fn render_to_png(wram_file : &str, rom_file : Option<&str>, out_file : &str) -> Result<(), String> {
+    let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    let status_font = ttf_context.load_font("FreeMonoBold.ttf", 16)?;
+    let frame = load_frame(wram_file, rom_file)?;
+    let mut canvas = MyCanvas::new(CANVAS_WIDTH, CANVAS_HEIGHT, &ttf_context, &status_font)?;
+    canvas.clear();
+    draw_game(&mut canvas, &frame)?;
+    draw_borders(&mut canvas)?;
+    draw_status(&mut canvas, &status_lines(&frame, false))?;
+    canvas.into_surface().save(out_file)
+}
+
+// Headless export of the whole room of a WRAM dump (or savestate)
+pub fn export_room_from_wram(wram_file : &str, rom_file : Option<&str>, out_file : &str, dump_file : Option<&str>) -> Result<(), String> {
+    let ttf_context = sdl2::ttf::init().map_err(|e| e.to_string())?;
+    let status_font = ttf_context.load_font("FreeMonoBold.ttf", 16)?;
+    let frame = load_frame(wram_file, rom_file)?;
+    export_room(&ttf_context, &status_font, &frame, out_file, dump_file)
+}
@@ -0,0 +1,46 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Error;
+use std::sync::Arc;
+
+// Super Metroid is LoROM, banks are 0x8000 bytes mapped at 0x8000-0xFFFF
+pub fn lorom_to_pc(address : u32) -> u32 {
+    ((address >> 16) & 0x7F) * 0x8000 + (address & 0x7FFF)
+}
+
+pub fn load_rom_file(path : &str) -> Result<Vec<u8>, Error> {
+    let mut rom = fs::read(path)?;
+    // Remove the copier header
+    if rom.len() % 0x8000 == 0x200 {
+        rom.drain(0..0x200);
+    }
+    Ok(rom)
+}
+
+// The ROM banks we have read, we never need the whole ROM
+#[derive(Clone, Default)]
+pub struct Rom {
+    banks : HashMap<u8, Arc<Vec<u8>>>
+}
+
+impl Rom {
+    pub fn new() -> Rom {
+        Rom::default()
+    }
+    pub fn has_bank(&self, bank : u8) -> bool {
+        self.banks.contains_key(&bank)
+    }
+    pub fn set_bank(&mut self, bank : u8, data : Vec<u8>) {
+        self.banks.insert(bank, Arc::new(data));
+    }
+    pub fn read_u8(&self, address : u32) -> Option<u8> {
+        let bank = self.banks.get(&((address >> 16) as u8))?;
+        bank.get((address & 0x7FFF) as usize).copied()
+    }
+    pub fn read_u16(&self, address : u32) -> Option<u16> {
+        Some(self.read_u8(address)? as u16 + self.read_u8(address + 1)? as u16 * 256)
+    }
+    pub fn read_bytes(&self, address : u32, size : usize) -> Option<Vec<u8>> {
+        (0..size as u32).map(|i| self.read_u8(address + i)).collect()
+    }
+}
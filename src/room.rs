@@ -0,0 +1,505 @@
+use crate::rom::Rom;
+
+// Room header in bank $8F, 11 bytes followed by the room state list
+#[derive(Clone)]
+pub struct RoomHeader {
+    pub pointer : u16,
+    pub index : u8,
+    pub area : u8,
+    pub map_x : u8,
+    pub map_y : u8,
+    pub width : u8,
+    pub height : u8,
+    pub up_scroller : u8,
+    pub down_scroller : u8,
+    pub door_list : u16,
+    // The door blocks BTS is the index in this list
+    pub doors : Vec<Door>,
+    // Condition code and room state pointer of every state
+    pub states : Vec<(u16, u16)>,
+    pub state : Option<RoomState>
+}
+
+// 26 bytes room state in bank $8F
+#[derive(Clone)]
+pub struct RoomState {
+    pub pointer : u16,
+    pub level_data : u32,
+    pub tileset : u8,
+    pub music_data : u8,
+    pub music_track : u8,
+    pub fx : u16,
+    pub enemy_population : u16,
+    pub enemy_set : u16,
+    pub layer2_scroll : u16,
+    pub scroll : u16,
+    pub xray : u16,
+    pub main_asm : u16,
+    pub plm_population : u16,
+    pub library_background : u16,
+    pub setup_asm : u16
+}
+
+// 12 bytes door entry in bank $83
+#[derive(Clone)]
+pub struct Door {
+    pub pointer : u16,
+    pub destination : u16,
+    pub flags : u8,
+    pub direction : u8,
+    pub cap_x : u8,
+    pub cap_y : u8,
+    pub screen_x : u8,
+    pub screen_y : u8,
+    pub spawn_distance : u16,
+    pub asm : u16
+}
+
+const STATE_DEFAULT : u16 = 0xE5E6;
+
+// Size of the argument of each state condition
+fn condition_argument_size(condition : u16) -> Option<u32> {
+    match condition {
+        0xE5EB => Some(2), // Door
+        0xE612 => Some(1), // Event
+        0xE629 => Some(1), // Boss
+        0xE5FF | 0xE640 | 0xE652 | 0xE669 | 0xE676 | 0xE678 => Some(0), // Tourian boss, morph, morph and missiles, power bombs, speed booster
+        _ => None
+    }
+}
+
+pub fn condition_name(condition : u16) -> &'static str {
+    match condition {
+        0xE5E6 => "Default",
+        0xE5EB => "Door",
+        0xE5FF => "Tourian boss",
+        0xE612 => "Event",
+        0xE629 => "Boss",
+        0xE640 => "Morph",
+        0xE652 => "Morph and missiles",
+        0xE669 => "Power bombs",
+        0xE676 | 0xE678 => "Speed booster",
+        _ => "Unknown"
+    }
+}
+
+pub fn parse_room_header(rom : &Rom, pointer : u16, state_pointer : u16) -> Option<RoomHeader> {
+    let address = 0x8F0000 + pointer as u32;
+    let header = rom.read_bytes(address, 11)?;
+    let door_list = header[9] as u16 + header[10] as u16 * 256;
+    let mut doors : Vec<Door> = vec![];
+    let mut door_address = 0x8F0000 + door_list as u32;
+    // The list has no terminator, the next thing in the bank is not a bank $83 pointer
+    while let Some(door) = rom.read_u16(door_address) {
+        if door < 0x8000 || doors.len() >= 64 {
+            break;
+        }
+        match parse_door(rom, door) {
+            Some(door) => doors.push(door),
+            None => break
+        }
+        door_address += 2;
+    }
+    let mut states : Vec<(u16, u16)> = vec![];
+    let mut state_address = address + 11;
+    loop {
+        let condition = rom.read_u16(state_address)?;
+        if condition == STATE_DEFAULT {
+            states.push((condition, (state_address + 2) as u16));
+            break;
+        }
+        let argument_size = condition_argument_size(condition)?;
+        states.push((condition, rom.read_u16(state_address + 2 + argument_size)?));
+        state_address += 4 + argument_size;
+    }
+    Some(RoomHeader {
+        pointer,
+        index : header[0],
+        area : header[1],
+        map_x : header[2],
+        map_y : header[3],
+        width : header[4],
+        height : header[5],
+        up_scroller : header[6],
+        down_scroller : header[7],
+        door_list,
+        doors,
+        states,
+        state : parse_room_state(rom, state_pointer)
+    })
+}
+
+pub fn parse_door(rom : &Rom, pointer : u16) -> Option<Door> {
+    let d = rom.read_bytes(0x830000 + pointer as u32, 12)?;
+    Some(Door {
+        pointer,
+        destination : d[0] as u16 + d[1] as u16 * 256,
+        flags : d[2],
+        direction : d[3],
+        cap_x : d[4],
+        cap_y : d[5],
+        screen_x : d[6],
+        screen_y : d[7],
+        spawn_distance : d[8] as u16 + d[9] as u16 * 256,
+        asm : d[10] as u16 + d[11] as u16 * 256
+    })
+}
+
+pub fn door_direction_name(direction : u8) -> &'static str {
+    match direction & 0x03 {
+        0 => "Right",
+        1 => "Left",
+        2 => "Down",
+        _ => "Up"
+    }
+}
+
+// Lines for the door tooltip
+pub fn door_description(index : usize, door : &Door) -> Vec<String> {
+    let destination = match room_name(door.destination) {
+        Some(name) => format!("{} (${:04X})", name, door.destination),
+        None => format!("Room ${:04X}", door.destination)
+    };
+    let mut lines : Vec<String> = vec![];
+    lines.push(format!("Door {} - $83:{:04X}", index, door.pointer));
+    lines.push(format!("To : {}", destination));
+    lines.push(format!("Direction : {}{}", door_direction_name(door.direction),
+        if door.direction & 0x04 != 0 { ", closes behind" } else { "" }));
+    if door.flags & 0x80 != 0 {
+        lines.push(String::from("Elevator"));
+    }
+    if door.flags & 0x40 != 0 {
+        lines.push(String::from("Changes area"));
+    }
+    if door.direction & 0x04 != 0 {
+        lines.push(format!("Cap : {},{}", door.cap_x, door.cap_y));
+    }
+    lines.push(format!("Screen : {},{}", door.screen_x, door.screen_y));
+    lines.push(format!("Spawn distance : ${:04X}", door.spawn_distance));
+    if door.asm != 0 {
+        lines.push(format!("Door ASM : $8F:{:04X}", door.asm));
+    } else {
+        lines.push(String::from("No door ASM"));
+    }
+    lines
+}
+
+pub fn parse_room_state(rom : &Rom, pointer : u16) -> Option<RoomState> {
+    let s = rom.read_bytes(0x8F0000 + pointer as u32, 26)?;
+    let word = |i : usize| s[i] as u16 + s[i + 1] as u16 * 256;
+    Some(RoomState {
+        pointer,
+        level_data : s[0] as u32 + s[1] as u32 * 256 + s[2] as u32 * 65536,
+        tileset : s[3],
+        music_data : s[4],
+        music_track : s[5],
+        fx : word(6),
+        enemy_population : word(8),
+        enemy_set : word(10),
+        layer2_scroll : word(12),
+        scroll : word(14),
+        xray : word(16),
+        main_asm : word(18),
+        plm_population : word(20),
+        library_background : word(22),
+        setup_asm : word(24)
+    })
+}
+
+pub fn area_name(area : u8) -> &'static str {
+    match area {
+        0 => "Crateria",
+        1 => "Brinstar",
+        2 => "Norfair",
+        3 => "Wrecked Ship",
+        4 => "Maridia",
+        5 => "Tourian",
+        6 => "Ceres",
+        7 => "Debug",
+        _ => "Unknown area"
+    }
+}
+
+pub fn room_name(pointer : u16) -> Option<&'static str> {
+    ROOM_NAMES.iter().find(|r| r.0 == pointer).map(|r| r.1)
+}
+
+// Room names are not in the ROM, rooms missing here are shown with their pointer
+static ROOM_NAMES : &[(u16, &str)] = &[
+    (0x91F8, "Landing Site"),
+    (0x92B3, "Gauntlet Entrance"),
+    (0x92FD, "Parlor and Alcatraz"),
+    (0x93AA, "Crateria Power Bomb Room"),
+    (0x93D5, "Crateria Save Room"),
+    (0x93FE, "West Ocean"),
+    (0x9461, "Bowling Alley Path"),
+    (0x948C, "Crateria Kihunter Room"),
+    (0x94CC, "Forgotten Highway Elevator"),
+    (0x94FD, "East Ocean"),
+    (0x9552, "Forgotten Highway Kago Room"),
+    (0x957D, "Crab Maze"),
+    (0x95A8, "Forgotten Highway Elbow"),
+    (0x95D4, "Crateria Tube"),
+    (0x95FF, "The Moat"),
+    (0x962A, "Red Brinstar Elevator Room"),
+    (0x965B, "Gauntlet Energy Tank Room"),
+    (0x968F, "Homing Geemer Room"),
+    (0x96BA, "Climb"),
+    (0x975C, "Pit Room"),
+    (0x97B5, "Blue Brinstar Elevator Room"),
+    (0x9804, "Bomb Torizo Room"),
+    (0x9879, "Flyway"),
+    (0x98E2, "Pre-Map Flyway"),
+    (0x990D, "Terminator Room"),
+    (0x9938, "Green Brinstar Elevator Room"),
+    (0x9969, "Lower Mushrooms"),
+    (0x9994, "Crateria Map Room"),
+    (0x99BD, "Green Pirates Shaft"),
+    (0x99F9, "Crateria Super Room"),
+    (0x9A44, "Final Missile Bombway"),
+    (0x9A90, "The Final Missile"),
+    (0x9AD9, "Green Brinstar Main Shaft"),
+    (0x9B5B, "Spore Spawn Super Room"),
+    (0x9B9D, "Brinstar Pre-Map Room"),
+    (0x9BC8, "Early Supers Room"),
+    (0x9C07, "Brinstar Reserve Tank Room"),
+    (0x9C35, "Brinstar Map Room"),
+    (0x9C5E, "Green Brinstar Fireflea Room"),
+    (0x9C89, "Green Brinstar Missile Refill Room"),
+    (0x9CB3, "Dachora Room"),
+    (0x9D19, "Big Pink"),
+    (0x9D9C, "Spore Spawn Kihunter Room"),
+    (0x9DC7, "Spore Spawn Room"),
+    (0x9E11, "Pink Brinstar Power Bomb Room"),
+    (0x9E52, "Green Hill Zone"),
+    (0x9E9F, "Morph Ball Room"),
+    (0x9F11, "Construction Zone"),
+    (0x9F64, "Blue Brinstar Energy Tank Room"),
+    (0x9FBA, "Noob Bridge"),
+    (0x9FE5, "Green Brinstar Beetom Room"),
+    (0xA011, "Etecoon Energy Tank Room"),
+    (0xA051, "Etecoon Super Room"),
+    (0xA07B, "Dachora Energy Refill Room"),
+    (0xA0A4, "Spore Spawn Farming Room"),
+    (0xA0D2, "Waterway Energy Tank Room"),
+    (0xA107, "First Missile Room"),
+    (0xA130, "Pink Brinstar Hopper Room"),
+    (0xA15B, "Hopper Energy Tank Room"),
+    (0xA184, "Big Pink Save Room"),
+    (0xA1AD, "Blue Brinstar Boulder Room"),
+    (0xA1D8, "Billy Mays Room"),
+    (0xA201, "Green Brinstar Main Shaft Save Room"),
+    (0xA22A, "Etecoon Save Room"),
+    (0xA253, "Red Tower"),
+    (0xA293, "Red Brinstar Fireflea Room"),
+    (0xA2CE, "X-Ray Scope Room"),
+    (0xA2F7, "Hellway"),
+    (0xA322, "Caterpillar Room"),
+    (0xA37C, "Beta Power Bomb Room"),
+    (0xA3AE, "Alpha Power Bomb Room"),
+    (0xA3DD, "Bat Room"),
+    (0xA408, "Below Spazer"),
+    (0xA447, "Spazer Room"),
+    (0xA471, "Warehouse Zeela Room"),
+    (0xA4B1, "Warehouse Energy Tank Room"),
+    (0xA4DA, "Warehouse Kihunter Room"),
+    (0xA521, "Baby Kraid Room"),
+    (0xA56B, "Kraid Eye Door Room"),
+    (0xA59F, "Kraid Room"),
+    (0xA5ED, "Statues Hallway"),
+    (0xA618, "Sloaters Refill"),
+    (0xA641, "Kraid Recharge Station"),
+    (0xA66A, "Statues Room"),
+    (0xA6A1, "Warehouse Entrance"),
+    (0xA6E2, "Varia Suit Room"),
+    (0xA70B, "Warehouse Save Room"),
+    (0xA75D, "Ice Beam Acid Room"),
+    (0xA788, "Cathedral"),
+    (0xA7B3, "Cathedral Entrance"),
+    (0xA7DE, "Business Center"),
+    (0xA815, "Ice Beam Gate Room"),
+    (0xA865, "Ice Beam Tutorial Room"),
+    (0xA890, "Ice Beam Room"),
+    (0xA8B9, "Ice Beam Snake Room"),
+    (0xA8F8, "Crumble Shaft"),
+    (0xA923, "Crocomire Speedway"),
+    (0xA98D, "Crocomire's Room"),
+    (0xA9E5, "Hi Jump Boots Room"),
+    (0xAA0E, "Crocomire Escape"),
+    (0xAA41, "Hi Jump Energy Tank Room"),
+    (0xAA82, "Post Crocomire Farming Room"),
+    (0xAAB5, "Post Crocomire Save Room"),
+    (0xAADE, "Post Crocomire Power Bomb Room"),
+    (0xAB07, "Post Crocomire Shaft"),
+    (0xAB3B, "Post Crocomire Missile Room"),
+    (0xAB64, "Grapple Tutorial Room 3"),
+    (0xAB8F, "Post Crocomire Jump Room"),
+    (0xABD2, "Grapple Tutorial Room 2"),
+    (0xAC00, "Grapple Tutorial Room 1"),
+    (0xAC2B, "Grapple Beam Room"),
+    (0xAC5A, "Norfair Reserve Tank Room"),
+    (0xAC83, "Green Bubbles Missile Room"),
+    (0xACB3, "Bubble Mountain"),
+    (0xACF0, "Speed Booster Hall"),
+    (0xAD1B, "Speed Booster Room"),
+    (0xAD5E, "Single Chamber"),
+    (0xADAD, "Double Chamber"),
+    (0xADDE, "Wave Beam Room"),
+    (0xAE07, "Spiky Platforms Tunnel"),
+    (0xAE32, "Volcano Room"),
+    (0xAE74, "Kronic Boost Room"),
+    (0xAEB4, "Magdollite Tunnel"),
+    (0xAEDF, "Purple Shaft"),
+    (0xAF14, "Lava Dive Room"),
+    (0xAF3F, "Lower Norfair Elevator"),
+    (0xAF72, "Upper Norfair Farming Room"),
+    (0xAFA3, "Rising Tide"),
+    (0xAFCE, "Acid Snakes Tunnel"),
+    (0xAFFB, "Spiky Acid Snakes Tunnel"),
+    (0xB026, "Nutella Refill"),
+    (0xB051, "Purple Farming Room"),
+    (0xB07A, "Bat Cave"),
+    (0xB0B4, "Norfair Map Room"),
+    (0xB0DD, "Bubble Mountain Save Room"),
+    (0xB106, "Frog Speedway"),
+    (0xB139, "Red Pirate Shaft"),
+    (0xB167, "Frog Savestation"),
+    (0xB192, "Crocomire Save Room"),
+    (0xB1BB, "Lower Norfair Elevator Save Room"),
+    (0xB1E5, "Acid Statue Room"),
+    (0xB236, "Main Hall"),
+    (0xB283, "Golden Torizo's Room"),
+    (0xB2DA, "Fast Ripper Room"),
+    (0xB305, "Golden Torizo Energy Recharge"),
+    (0xB32E, "Ridley's Room"),
+    (0xB37A, "Lower Norfair Farming Room"),
+    (0xB3A5, "Fast Pillars Setup Room"),
+    (0xB40A, "Mickey Mouse Room"),
+    (0xB457, "Pillar Room"),
+    (0xB482, "Plowerhouse Room"),
+    (0xB4AD, "The Worst Room In The Game"),
+    (0xB4E5, "Amphitheatre"),
+    (0xB510, "Lower Norfair Spring Ball Maze Room"),
+    (0xB55A, "Lower Norfair Escape Power Bomb Room"),
+    (0xB585, "Red Kihunter Shaft"),
+    (0xB5D5, "Wasteland"),
+    (0xB62B, "Metal Pirates Room"),
+    (0xB656, "Three Musketeers' Room"),
+    (0xB698, "Ridley Tank Room"),
+    (0xB6C1, "Screw Attack Room"),
+    (0xB6EE, "Lower Norfair Fireflea Room"),
+    (0xB741, "Red Kihunter Shaft Save Room"),
+    (0xC98E, "Bowling Alley"),
+    (0xCA08, "Wrecked Ship Entrance"),
+    (0xCA52, "Attic"),
+    (0xCAAE, "Assembly Line"),
+    (0xCAF6, "Wrecked Ship Main Shaft"),
+    (0xCB8B, "Spiky Death Room"),
+    (0xCBD5, "Electric Death Room"),
+    (0xCC27, "Wrecked Ship Energy Tank Room"),
+    (0xCC6F, "Basement"),
+    (0xCCCB, "Wrecked Ship Map Room"),
+    (0xCD13, "Phantoon's Room"),
+    (0xCD5C, "Sponge Bath"),
+    (0xCDA8, "Wrecked Ship West Super Room"),
+    (0xCDF1, "Wrecked Ship East Super Room"),
+    (0xCE40, "Gravity Suit Room"),
+    (0xCE8A, "Wrecked Ship Save Room"),
+    (0xCED2, "Glass Tunnel Save Room"),
+    (0xCEFB, "Glass Tunnel"),
+    (0xCF54, "West Tunnel"),
+    (0xCF80, "East Tunnel"),
+    (0xCFC9, "Main Street"),
+    (0xD017, "Fish Tank"),
+    (0xD055, "Mama Turtle Room"),
+    (0xD08A, "Crab Tunnel"),
+    (0xD0B9, "Mt. Everest"),
+    (0xD104, "Red Fish Room"),
+    (0xD13B, "Watering Hole"),
+    (0xD16D, "Northwest Maridia Bug Room"),
+    (0xD1A3, "Crab Shaft"),
+    (0xD1DD, "Pseudo Plasma Spark Room"),
+    (0xD21C, "Crab Hole"),
+    (0xD252, "West Sand Hall Tunnel"),
+    (0xD27E, "Plasma Tutorial Room"),
+    (0xD2AA, "Plasma Room"),
+    (0xD2D9, "Thread The Needle Room"),
+    (0xD30B, "Maridia Elevator Room"),
+    (0xD340, "Plasma Spark Room"),
+    (0xD387, "Kassiuz Room"),
+    (0xD3B6, "Maridia Map Room"),
+    (0xD3DF, "Forgotten Highway Save Room"),
+    (0xD408, "Toilet"),
+    (0xD433, "Bug Sand Hole"),
+    (0xD461, "West Sand Hall"),
+    (0xD48E, "Oasis"),
+    (0xD4C2, "East Sand Hall"),
+    (0xD4EF, "West Sand Hole"),
+    (0xD51E, "East Sand Hole"),
+    (0xD54D, "West Aqueduct Quicksand Room"),
+    (0xD57A, "East Aqueduct Quicksand Room"),
+    (0xD5A7, "Aqueduct"),
+    (0xD5EC, "Butterfly Room"),
+    (0xD617, "Botwoon Hallway"),
+    (0xD646, "Pants Room"),
+    (0xD69A, "East Pants Room"),
+    (0xD6D0, "Spring Ball Room"),
+    (0xD6FD, "Below Botwoon Energy Tank"),
+    (0xD72A, "Colosseum"),
+    (0xD765, "Aqueduct Save Room"),
+    (0xD78F, "The Precious Room"),
+    (0xD7E4, "Botwoon Energy Tank Room"),
+    (0xD81A, "Draygon Save Room"),
+    (0xD845, "Maridia Missile Refill Room"),
+    (0xD86E, "Plasma Beach Quicksand Room"),
+    (0xD898, "Botwoon Quicksand Room"),
+    (0xD8C5, "Shaktool Room"),
+    (0xD913, "Halfie Climb Room"),
+    (0xD95E, "Botwoon's Room"),
+    (0xD9AA, "Space Jump Room"),
+    (0xD9D4, "Maridia Energy Refill Room"),
+    (0xD9FE, "West Cactus Alley Room"),
+    (0xDA2B, "East Cactus Alley Room"),
+    (0xDA60, "Draygon's Room"),
+    (0xDAAE, "Tourian First Room"),
+    (0xDAE1, "Metroid Room 1"),
+    (0xDB31, "Metroid Room 2"),
+    (0xDB7D, "Metroid Room 3"),
+    (0xDBCD, "Metroid Room 4"),
+    (0xDC19, "Blue Hopper Room"),
+    (0xDC65, "Dust Torizo Room"),
+    (0xDCB1, "Big Boy Room"),
+    (0xDCFF, "Seaweed Room"),
+    (0xDD2E, "Tourian Recharge Room"),
+    (0xDD58, "Mother Brain Room"),
+    (0xDDC4, "Tourian Eye Door Room"),
+    (0xDDF3, "Rinka Shaft"),
+    (0xDE23, "Mother Brain Save Room"),
+    (0xDE4D, "Tourian Escape Room 1"),
+    (0xDE7A, "Tourian Escape Room 2"),
+    (0xDEA7, "Tourian Escape Room 3"),
+    (0xDEDE, "Tourian Escape Room 4"),
+    (0xDF1B, "Upper Tourian Save Room"),
+    (0xDF45, "Ceres Elevator Room"),
+    (0xDF8D, "Falling Tile Room"),
+    (0xDFD7, "Magnet Stairs Room"),
+    (0xE021, "Dead Scientist Room"),
+    (0xE06B, "58 Escape"),
+    (0xE0B5, "Ceres Ridley's Room")
+];
+
+// Short text for the status bar
+pub fn room_description(room : &RoomHeader) -> String {
+    let name = match room_name(room.pointer) {
+        Some(name) => String::from(name),
+        None => format!("Room ${:04X}", room.pointer)
+    };
+    let state = match &room.state {
+        Some(state) => {
+            let condition = room.states.iter().find(|s| s.1 == state.pointer).map(|s| s.0).unwrap_or(STATE_DEFAULT);
+            format!("{} state ${:04X}", condition_name(condition), state.pointer)
+        },
+        None => String::from("unknown state")
+    };
+    format!("{} - {} ({},{}) - {}x{} screens - {}", name, area_name(room.area), room.map_x, room.map_y, room.width, room.height, state)
+}
@@ -0,0 +1,54 @@
+// Samus movement values, speeds are pixels and 1/65536 of pixel
+#[derive(Clone, Default)]
+pub struct SamusMovement {
+    pub subpixel_x : u16,
+    pub subpixel_y : u16,
+    pub speed_x : u16,
+    pub subspeed_x : u16,
+    pub momentum_x : u16,
+    pub submomentum_x : u16,
+    pub speed_y : u16,
+    pub subspeed_y : u16,
+    pub pose : u16,
+    // 0x04 facing left, 0x08 facing right
+    pub direction : u8,
+    pub movement_type : u8
+}
+
+// The speeds are read in one block from $7E:0B2C up to the X submomentum
+pub const SPEED_BLOCK_SIZE : usize = 0x1E;
+pub const POSE_BLOCK_SIZE : usize = 4;
+
+fn word(bytes : &[u8], offset : usize) -> u16 {
+    bytes[offset] as u16 + bytes[offset + 1] as u16 * 256
+}
+
+// Offsets in the speed block are address - 0x0B2C, the pose block is pose, direction and movement type from $7E:0A1C
+pub fn parse_movement(subpixel_x : &[u8], subpixel_y : &[u8], speeds : &[u8], pose : &[u8]) -> SamusMovement {
+    SamusMovement {
+        subpixel_x : word(subpixel_x, 0),
+        subpixel_y : word(subpixel_y, 0),
+        subspeed_y : word(speeds, 0x00),
+        speed_y : word(speeds, 0x02),
+        speed_x : word(speeds, 0x16),
+        subspeed_x : word(speeds, 0x18),
+        momentum_x : word(speeds, 0x1A),
+        submomentum_x : word(speeds, 0x1C),
+        pose : word(pose, 0),
+        direction : pose[2],
+        movement_type : pose[3]
+    }
+}
+
+// Pixel and subpixel like the practice hacks show them
+pub fn format_subpixel(pixel : i32, subpixel : u16) -> String {
+    format!("{}.{:04X}", pixel, subpixel)
+}
+
+pub fn direction_name(direction : u8) -> &'static str {
+    match direction {
+        0x04 => "left",
+        0x08 => "right",
+        _ => "forward"
+    }
+}
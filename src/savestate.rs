@@ -0,0 +1,120 @@
+use std::fs;
+use std::io::{Read, Error, ErrorKind};
+use flate2::read::GzDecoder;
+use crate::memorysource::WRAM_SIZE;
+
+// Extract the WRAM from an emulator savestate, snes9x and bsnes formats
+// are understood, a raw 128 KiB WRAM dump works too
+pub fn load_savestate_wram(path : &str) -> Result<Vec<u8>, Error> {
+    let mut state = fs::read(path)?;
+    if state.len() == WRAM_SIZE {
+        return Ok(state);
+    }
+    // snes9x compress its savestates with gzip by default
+    if state.starts_with(&[0x1F, 0x8B]) {
+        let mut decompressed : Vec<u8> = vec![];
+        GzDecoder::new(state.as_slice()).read_to_end(&mut decompressed)?;
+        state = decompressed;
+    }
+    if state.starts_with(b"#!s9xsnp") {
+        return snes9x_wram(&state);
+    }
+    if !state.starts_with(b"BST1") {
+        state = bsnes_decode(&state)?;
+    }
+    match find_wram(&state) {
+        Some(offset) => Ok(state[offset..offset + WRAM_SIZE].to_vec()),
+        None => Err(Error::new(ErrorKind::InvalidData, "Can't find Super Metroid WRAM in the savestate"))
+    }
+}
+
+// snes9x snapshot is a list of "NAM:000000:" headers followed by the block data
+pub fn snes9x_wram(state : &[u8]) -> Result<Vec<u8>, Error> {
+    let mut pos = match state.iter().position(|b| *b == b'\n') {
+        Some(p) => p + 1,
+        None => return Err(Error::new(ErrorKind::InvalidData, "Invalid snes9x savestate"))
+    };
+    while pos + 11 <= state.len() {
+        let header = &state[pos..pos + 11];
+        let size : usize = std::str::from_utf8(&header[4..10]).ok().and_then(|size| size.parse().ok())
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Invalid snes9x block header"))?;
+        pos += 11;
+        if &header[0..3] == b"RAM" {
+            if size != WRAM_SIZE || pos + size > state.len() {
+                return Err(Error::new(ErrorKind::InvalidData, "The snes9x RAM block is truncated"));
+            }
+            return Ok(state[pos..pos + size].to_vec());
+        }
+        pos += size;
+    }
+    Err(Error::new(ErrorKind::InvalidData, "No RAM block in the snes9x savestate"))
+}
+
+// bsnes (v107+) savestates : version, serializer size, preview size then the
+// serializer data compressed with nall RLE
+pub fn bsnes_decode(state : &[u8]) -> Result<Vec<u8>, Error> {
+    if state.len() < 12 {
+        return Err(Error::new(ErrorKind::InvalidData, "Unknown savestate format"));
+    }
+    let rle_size = u32::from_le_bytes([state[4], state[5], state[6], state[7]]) as usize;
+    if 12 + rle_size > state.len() {
+        return Err(Error::new(ErrorKind::InvalidData, "Unknown savestate format"));
+    }
+    let data = rle_decode(&state[12..12 + rle_size]);
+    if !data.starts_with(b"BST1") {
+        return Err(Error::new(ErrorKind::InvalidData, "Unknown savestate format"));
+    }
+    Ok(data)
+}
+
+pub fn rle_decode(input : &[u8]) -> Vec<u8> {
+    let mut pos = 0;
+    let mut load = || -> u8 {
+        pos += 1;
+        if pos <= input.len() { input[pos - 1] } else { 0 }
+    };
+    let mut size : usize = 0;
+    for byte in 0..8 {
+        size |= (load() as usize) << (byte * 8);
+    }
+    // Don't trust a broken header to allocate the whole memory
+    let size = size.min(input.len() * 132);
+    let mut output : Vec<u8> = Vec::with_capacity(size);
+    while output.len() < size {
+        let byte = load();
+        if byte < 128 {
+            for _ in 0..(byte as usize + 1) {
+                output.push(load());
+            }
+        } else {
+            let value = load();
+            for _ in 0..((byte & 127) as usize + 4) {
+                output.push(value);
+            }
+        }
+    }
+    output.truncate(size);
+    output
+}
+
+// The WRAM position in the serialized state depends on the emulator version,
+// look for something that looks like Super Metroid in a room instead :
+// a room pointer in bank $8F, room size in blocks and the level data size
+// at 0x7F0000 matching the room size
+pub fn find_wram(state : &[u8]) -> Option<usize> {
+    if state.len() < WRAM_SIZE {
+        return None;
+    }
+    let word = |offset : usize| -> usize {
+        state[offset] as usize + state[offset + 1] as usize * 256
+    };
+    (0..=state.len() - WRAM_SIZE).find(|offset| {
+        let room = word(offset + 0x079B);
+        let width = word(offset + 0x07A5);
+        let height = word(offset + 0x07A7);
+        (0x91F8..0xE000).contains(&room)
+            && width != 0 && width <= 0x100 && width % 16 == 0
+            && height != 0 && height <= 0x100 && height % 16 == 0
+            && word(offset + 0x10000) == width * height * 2
+    })
+}
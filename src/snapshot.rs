@@ -0,0 +1,36 @@
+use std::sync::{Arc, Mutex};
+use arc_swap::ArcSwap;
+use crate::data::{SharedData, View};
+
+// Hand-off between the polling thread and the renderer : the polling thread works on its own
+// SharedData and publishes a copy of it after each read, the renderer takes the last one.
+// Neither side waits for the other, the level data, ROM, overlays and trail are shared, not copied
+pub struct FrameChannel {
+    latest : ArcSwap<SharedData>,
+    // Goes the other way : what the renderer draws, so the polling thread reads the right rows
+    view : Mutex<View>
+}
+
+impl FrameChannel {
+    pub fn new(data : SharedData) -> FrameChannel {
+        let view = data.view;
+        FrameChannel {
+            latest : ArcSwap::from_pointee(data),
+            view : Mutex::new(view)
+        }
+    }
+    // Give the next version number to the data and make it the latest snapshot
+    pub fn publish(&self, data : &mut SharedData) {
+        data.version += 1;
+        self.latest.store(Arc::new(data.clone()));
+    }
+    pub fn latest(&self) -> Arc<SharedData> {
+        self.latest.load_full()
+    }
+    pub fn set_view(&self, view : View) {
+        *self.view.lock().unwrap() = view;
+    }
+    pub fn view(&self) -> View {
+        *self.view.lock().unwrap()
+    }
+}
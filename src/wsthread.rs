@@ -1,30 +1,55 @@
-use std::sync::{Mutex, Arc};
-use tungstenite::error::Error;
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use crate::data::{self, SharedData};
-use crate::data::Usb2SnesError;
+use crate::data::{GamePhase, Usb2SnesError, View};
+use crate::enemy::{self, ENEMY_SLOTS, ENEMY_SLOT_SIZE};
+use crate::projectile::{self, PROJECTILE_ARRAY_SIZE, ENEMY_PROJECTILE_ARRAY_SIZE};
+use crate::plm::{self, PLM_ARRAY_SIZE};
+use crate::samus::{self, SPEED_BLOCK_SIZE, POSE_BLOCK_SIZE};
+use crate::memorysource::{Error, MemorySource, Usb2SnesClient, wram};
+use crate::recording::{Recorder, RecordingSource};
+use crate::rom::lorom_to_pc;
+use crate::render;
+use crate::room;
+use crate::snapshot::FrameChannel;
 use rusb2snes;
 use rusb2snes::SyncClient;
 
+// Wait before trying again to connect or to find a game, half of it to find a device
+pub const RETRY_DELAY : Duration = Duration::from_secs(2);
 
-pub fn wsthread(data : Arc<Mutex<data::SharedData>>) {
+pub fn wsthread(channel : Arc<FrameChannel>, record_file : Option<String>, retry_delay : Duration) {
+    wsthread_with(try_to_connect, channel, record_file, retry_delay)
+}
+
+// The usb2snes loop with another way to connect, the tests use it to reach a mock server
+pub fn wsthread_with<C : Usb2SnesClient, F : Fn() -> Result<C, Error>>(connect : F, channel : Arc<FrameChannel>, record_file : Option<String>, retry_delay : Duration) {
+    let mut data = (*channel.latest()).clone();
+    let mut recorder : Option<Recorder> = None;
+    if let Some(path) = record_file {
+        match Recorder::new(&path) {
+            Ok(r) => recorder = Some(r),
+            Err(err) => println!("Can't record to {} : {}", path, err)
+        }
+    }
     'main : loop {
-        let mut usb2snes : SyncClient;
-        let co_return = try_to_connect();
+        let mut usb2snes : C;
+        let co_return = connect();
         match co_return {
             Ok(plop) => usb2snes = plop,
             Err(_err) => {
-                std::thread::sleep(Duration::new(2, 0));
-                let mut mutex = data.lock().unwrap();
-                (*mutex).usb2snes_error = Usb2SnesError::CantConnect;
+                std::thread::sleep(retry_delay);
+                data.usb2snes_error = Usb2SnesError::CantConnect;
+                channel.publish(&mut data);
                 continue;
             }
         }
         '_attach : loop {
-            fn list_device(usb2snes : &mut SyncClient) -> Result<i32, Error> {
-                let devices = usb2snes.list_device()?;
-                if devices.len() != 0 {
-                    usb2snes.attach(&devices[0])?;
+            fn list_device<C : Usb2SnesClient>(usb2snes : &mut C) -> Result<i32, Error> {
+                let devices = usb2snes.list_devices()?;
+                if !devices.is_empty() {
+                    usb2snes.attach_device(&devices[0])?;
                 } else {
                     return Ok(0);
                 }
@@ -32,31 +57,31 @@ pub fn wsthread(data : Arc<Mutex<data::SharedData>>) {
             }
             match list_device(&mut usb2snes) {
                 Err(_err) => {
-                    let mut mutex = data.lock().unwrap();
-                    (*mutex).usb2snes_error = Usb2SnesError::CantAttach;
+                    data.usb2snes_error = Usb2SnesError::CantAttach;
+                    channel.publish(&mut data);
                     continue 'main
                 },
                 Ok(nb_device) => {
                     if nb_device == 1 {
                         break;
                     } else {
-                        let mut mutex = data.lock().unwrap();
-                        (*mutex).usb2snes_error = Usb2SnesError::NoDevice;
-                        std::thread::sleep_ms(1000);
+                        data.usb2snes_error = Usb2SnesError::NoDevice;
+                        channel.publish(&mut data);
+                        std::thread::sleep(retry_delay / 2);
                     }
                 }
             }
         }
         // Let do stuff finally, just kidding, we need a game
         loop {
-            let infos = usb2snes.info();
+            let infos = usb2snes.game();
             match infos {
                 Err(_err) => {continue 'main}
-                Ok(info) => {
-                    if info.game == "/boot/menu.bin" || info.game == "/boot/m3nu.bin" {
-                        let mut mutex = data.lock().unwrap();
-                        (*mutex).usb2snes_error = Usb2SnesError::NoGame;
-                        std::thread::sleep_ms(2000);
+                Ok(game) => {
+                    if game == "/boot/menu.bin" || game == "/boot/m3nu.bin" {
+                        data.usb2snes_error = Usb2SnesError::NoGame;
+                        channel.publish(&mut data);
+                        std::thread::sleep(retry_delay);
                         continue;
                     } else {
                         break;
@@ -64,98 +89,288 @@ pub fn wsthread(data : Arc<Mutex<data::SharedData>>) {
                 }
             }
         }
-        {
-            let mut mutex = data.lock().unwrap();
-            (*mutex).usb2snes_ready = true;
-        }
+        data.usb2snes_ready = true;
+        channel.publish(&mut data);
         loop {
-            match actually_getting_data(&mut usb2snes, &data) {
+            let poll_start = Instant::now();
+            data.view = channel.view();
+            let result = match &mut recorder {
+                Some(recorder) => actually_getting_data(&mut RecordingSource::new(&mut usb2snes, recorder), &mut data),
+                None => actually_getting_data(&mut usb2snes, &mut data)
+            };
+            match result {
                 Err(_err) => {continue 'main},
-                Ok(()) => {}
+                // The next frame comes ~16.7 ms after a new one, no need to ask before.
+                // Then ask often so we see every frame as soon as possible
+                Ok(new_frame) => {
+                    channel.publish(&mut data);
+                    let wait = if new_frame { Duration::from_millis(12) } else { Duration::from_millis(1) };
+                    std::thread::sleep(wait.saturating_sub(poll_start.elapsed()));
+                }
             }
         }
     }
 }
 
+// Polling loop for sources that are always available, like a savestate
+pub fn source_thread<S : MemorySource>(mut source : S, channel : Arc<FrameChannel>) {
+    let mut data = (*channel.latest()).clone();
+    data.usb2snes_ready = true;
+    channel.publish(&mut data);
+    loop {
+        data.view = channel.view();
+        if actually_getting_data(&mut source, &mut data).is_err() {
+            data.usb2snes_ready = false;
+            channel.publish(&mut data);
+            return;
+        }
+        channel.publish(&mut data);
+        std::thread::sleep(Duration::from_millis(16));
+    }
+}
+
 // Thing to read each frame
 const A_GAME_STATE : u32 = 0x7E0998;
-const A_MAP_ID : u32 = 0x7E079B;
 const A_SAMUS_X : u32 = 0x7E0AF6;
 const A_SAMUS_Y : u32 = 0x7E0AFA;
 const A_RADIUS_X : u32 = 0x7E0AFE;
 const A_RADIUS_Y : u32 = 0x7E0B00;
 const A_WIDTH : u32 = 0x7E07A5;
 const A_DOOR_STUFF : u32 = 0x7E07B5;
+const A_HEIGHT : u32 = 0x7E07A7;
+const A_ROOM_POINTER : u32 = 0x7E079B;
+const A_ROOM_STATE : u32 = 0x7E07BB;
+const A_ENEMIES : u32 = 0x7E0F78;
+const A_PROJECTILE_X : u32 = 0x7E0B64;
+const A_PROJECTILE_Y : u32 = 0x7E0B78;
+const A_PROJECTILE_RADIUS_X : u32 = 0x7E0BB4;
+const A_PROJECTILE_RADIUS_Y : u32 = 0x7E0BC8;
+const A_PROJECTILE_TYPE : u32 = 0x7E0C18;
+const A_PLM_IDS : u32 = 0x7E1C37;
+const A_PLM_BLOCKS : u32 = 0x7E1C87;
+const A_ENEMY_PROJECTILE_IDS : u32 = 0x7E1997;
+const A_ENEMY_PROJECTILE_X : u32 = 0x7E1A4B;
+const A_ENEMY_PROJECTILE_Y : u32 = 0x7E1A93;
+const A_ENEMY_PROJECTILE_RADIUS_X : u32 = 0x7E1BB3;
+const A_ENEMY_PROJECTILE_RADIUS_Y : u32 = 0x7E1BD7;
+const A_SCROLLS : u32 = 0x7ECD20;
+const A_LAYER1_X : u32 = 0x7E0911;
+const A_LAYER1_Y : u32 = 0x7E0915;
+// One scroll byte per screen of the room
+const SCROLLS_SIZE : usize = 0x32;
+const A_SAMUS_SUBPIXEL_X : u32 = 0x7E0AF8;
+const A_SAMUS_SUBPIXEL_Y : u32 = 0x7E0AFC;
+const A_SAMUS_SPEEDS : u32 = 0x7E0B2C;
+const A_SAMUS_POSE : u32 = 0x7E0A1C;
+const A_FRAME_COUNTER : u32 = 0x7E05B6;
 // var BTS = 0x7F0000 + ((0x6402 + a) % 0x10000)
 // var BTSvalue = memory.readUnsignedByte(BTS);
 // var Clip = 0x7F0000 + ((0x0002 + a * 2) % 0x10000)
 // var ClipValue = memory.readUnsignedWord(Clip)
 
 
-fn actually_getting_data(usb2snes : &mut SyncClient, data : &Mutex<SharedData>) -> Result<(), Error> {
-    static mut old_map_id : u16 = 0;
-    let bytes = get_base_wram_value(usb2snes)?;
-    let samus = sdl2::rect::Point::new(get_uword(bytes[1], bytes[2]).into(),
-                                              get_uword(bytes[3], bytes[4]).into());
-    let mut camera = sdl2::rect::Point::new((samus.x - 256) & 0xFFFF, (samus.y - 224) & 0xFFFF);
-    if camera.x >= 10000 {
-        camera.x = camera.x - 65535
-    }
-    if camera.y >= 10000 {
-        camera.y = camera.y - 65535
-    }
-    let map_id = bytes[0];
-    let game_state = bytes[11];
-    let width = get_uword(bytes[9], bytes[10]);
+// Read everything for one frame, true when this is a new frame of the game
+pub fn actually_getting_data<S : MemorySource>(source : &mut S, data : &mut SharedData) -> Result<bool, Error> {
+    let bytes = get_base_wram_value(source)?;
+    let samus = sdl2::rect::Point::new(get_uword(bytes[0], bytes[1]).into(),
+                                              get_uword(bytes[2], bytes[3]).into());
+    let game_state = bytes[10];
+    let width = get_uword(bytes[8], bytes[9]);
+    let room_pointer = get_uword(bytes[15], bytes[16]);
+    let state_pointer = get_uword(bytes[17], bytes[18]);
     /*let a : i32 = ((camera.x + x * 16) & 0xFFFF) / 16 + ((((camera.y + y * 16) & 0xFFF) / 16) * (width as i32) & 0xFFFF);
     let bts : usize = (0x6402 as usize + a as usize) % 0x10000;
     let plop = usb2snes.get_address(bts as u32, 1)?;*/
-    /*if (map_id == 0) {
-        usb2snes.get_address(0x8F0000, size)
-    }*/
-    unsafe {
-    if map_id as u16 != old_map_id {
-        if (game_state == 0x08) {
-            let mapinfos = usb2snes.get_address(0xF60000, 0x10000)?;
-            let mut mutex =  data.lock().unwrap();
-            (*mutex).map_data = mapinfos;
-            old_map_id = map_id as u16;
-        } else {
-            return Ok(())
-        }
+    let phase = data::game_phase(game_state);
+    // The frame counter is the last value read, it goes on during transitions so follow it
+    // there too, or every door would count as dropped frames
+    let new_frame = data.set_frame_counter(get_uword(bytes[bytes.len() - 2], bytes[bytes.len() - 1]));
+    // Level data is read once each time we enter a room : a transition forgets the room,
+    // the next gameplay frame reads the new one
+    data.game_state = game_state;
+    data.game_phase = phase;
+    let reload = match phase {
+        GamePhase::Menu | GamePhase::Loading | GamePhase::DoorTransition => {
+            data.loaded_room = None;
+            false
+        },
+        GamePhase::Gameplay | GamePhase::Demo => data.loaded_room != Some(room_pointer),
+        _ => false
+    };
+    if reload {
+        // Both start as the same level data, refreshing the visible rows makes map_data its own copy
+        let mapinfos = Arc::new(source.read(wram(0x7F0000), 0x10000)?);
+        data.room_map_data = Arc::clone(&mapinfos);
+        data.map_data = mapinfos;
+        data.loaded_room = Some(room_pointer);
+        data.level_refreshed_at = None;
+        data.room_refresh_row = 0;
+        // The trail of the last room would be drawn over this one, a frozen trail is kept by the renderer
+        data.trail = Arc::new(VecDeque::with_capacity(data::TRAIL_LENGTH));
+    }
+    if data.loaded_room.is_none() {
+        return Ok(new_frame)
+    }
+    let room_changed = match &data.room {
+        Some(room) => room.pointer != room_pointer || room.state.as_ref().map(|s| s.pointer) != Some(state_pointer),
+        None => true
+    };
+    if room_changed {
+        load_rom_banks(source, data, &[0x8F, 0x83, 0x94, 0xA0, 0xB4])?;
+        data.room = room::parse_room_header(&data.rom_data, room_pointer, state_pointer);
     }
-    }    //println!("Before lock");
-    let mut mutex =  data.lock().unwrap();
     //println!("{}, {}", samus.x, samus.y);
-    (*mutex).door_stuff = get_uword(bytes[12], bytes[13]);
-    (*mutex).samus_pos = samus;
-    (*mutex).camera = camera;
-    (*mutex).width = width;
-    (*mutex).radius = sdl2::rect::Point::new(get_uword(bytes[5], bytes[6]).into(), get_uword(bytes[7], bytes[8]).into());
-    //(*mutex).bts_byte = plop[0];
+    data.door_stuff = get_uword(bytes[11], bytes[12]);
+    data.samus_pos = samus;
+    data.width = width;
+    data.height = get_uword(bytes[13], bytes[14]);
+    data.radius = sdl2::rect::Point::new(get_uword(bytes[4], bytes[5]).into(), get_uword(bytes[6], bytes[7]).into());
+    // The tables follow the 19 bytes of single values, in the order of get_base_wram_value
+    let mut tables = &bytes[19..];
+    let mut next = |size : usize| {
+        let (table, rest) = tables.split_at(size);
+        tables = rest;
+        table
+    };
+    data.enemies = Arc::new(enemy::parse_enemies(next(ENEMY_SLOTS * ENEMY_SLOT_SIZE)));
+    let p = [next(PROJECTILE_ARRAY_SIZE), next(PROJECTILE_ARRAY_SIZE), next(PROJECTILE_ARRAY_SIZE), next(PROJECTILE_ARRAY_SIZE), next(PROJECTILE_ARRAY_SIZE)];
+    data.projectiles = Arc::new(projectile::parse_projectiles(p[0], p[1], p[2], p[3], p[4]));
+    let (plm_ids, plm_blocks) = (next(PLM_ARRAY_SIZE), next(PLM_ARRAY_SIZE));
+    data.plms = Arc::new(plm::parse_plms(plm_ids, plm_blocks));
+    let e = [next(ENEMY_PROJECTILE_ARRAY_SIZE), next(ENEMY_PROJECTILE_ARRAY_SIZE), next(ENEMY_PROJECTILE_ARRAY_SIZE), next(ENEMY_PROJECTILE_ARRAY_SIZE), next(ENEMY_PROJECTILE_ARRAY_SIZE)];
+    data.enemy_projectiles = Arc::new(projectile::parse_enemy_projectiles(e[0], e[1], e[2], e[3], e[4]));
+    data.scrolls = Arc::new(next(SCROLLS_SIZE).to_vec());
+    let (layer1_x, layer1_y) = (next(2), next(2));
+    data.layer1 = sdl2::rect::Point::new(get_uword(layer1_x[0], layer1_x[1]).into(), get_uword(layer1_y[0], layer1_y[1]).into());
+    let (subpixel_x, subpixel_y, speeds, pose) = (next(2), next(2), next(SPEED_BLOCK_SIZE), next(POSE_BLOCK_SIZE));
+    data.movement = samus::parse_movement(subpixel_x, subpixel_y, speeds, pose);
+    // The reads of a poll are not all done at once, the room can be reloaded on a frame
+    // we already had : the trail starts there anyway
+    if new_frame || data.trail.is_empty() {
+        data.push_trail();
+    }
+    data.camera = render::game_camera(data.layer1);
+    //data.bts_byte = plop[0];
+    // Blocks break and respawn during the room, read again the rows the renderer shows every few frames
+    let refresh = data.level_refresh != 0 && !data.map_data.is_empty() && width != 0 && match (data.frame_counter, data.level_refreshed_at) {
+        (Some(frame), Some(refreshed)) => frame.wrapping_sub(refreshed) >= data.level_refresh,
+        _ => true
+    };
+    if refresh {
+        data.level_refreshed_at = data.frame_counter;
+        let rows = if data.height != 0 { data.height as i32 } else { 0x3200 / width as i32 };
+        let (first_row, last_row) = match data.view {
+            // As many rows as in the other views, the next ones on the next refresh
+            View::Room => {
+                let first_row = if data.room_refresh_row < rows { data.room_refresh_row } else { 0 };
+                data.room_refresh_row = first_row + 29;
+                (first_row, first_row + 29)
+            },
+            view => {
+                let top = if view == View::Samus { render::samus_camera(data).y } else { data.camera.y };
+                (top.div_euclid(16), top.div_euclid(16) + 29)
+            }
+        };
+        let (first_row, last_row) = (first_row.clamp(0, rows), last_row.clamp(0, rows));
+        if last_row > first_row {
+            refresh_level_rows(source, data, first_row as usize * width as usize, (last_row - first_row) as usize * width as usize)?;
+        }
+    }
+    Ok(new_frame)
+}
+
+// Read again the clip words and BTS of the level data, from the tile index start.
+// The level data is only copied when a block changed and a snapshot still uses it
+fn refresh_level_rows<S : MemorySource>(source : &mut S, data : &mut SharedData, start : usize, tiles : usize) -> Result<(), Error> {
+    // Two single reads, recordings take every read_multi for a new poll
+    let clips = source.read(wram(0x7F0002 + start as u32 * 2), tiles * 2)?;
+    let btss = source.read(wram(0x7F6402 + start as u32), tiles)?;
+    let clip = 0x0002 + start * 2;
+    let bts = 0x6402 + start;
+    if data.map_data.len() < bts + tiles {
+        return Ok(());
+    }
+    if data.map_data[clip..clip + tiles * 2] == clips[..] && data.map_data[bts..bts + tiles] == btss[..] {
+        return Ok(());
+    }
+    let map_data = Arc::make_mut(&mut data.map_data);
+    map_data[clip..clip + tiles * 2].copy_from_slice(&clips);
+    map_data[bts..bts + tiles].copy_from_slice(&btss);
     Ok(())
 }
 
-fn get_base_wram_value(usb2snes : &mut SyncClient) -> Result<Vec<u8>, Error> {
-    let mut address : Vec<u32> = vec![0; 8];
-    let mut sizes : Vec<usize> = vec![2;8];
-    address[0] = A_MAP_ID - 0x7E0000 + 0xF50000;
-    address[1] = A_SAMUS_X - 0x7E0000 + 0xF50000;
-    address[2] = A_SAMUS_Y - 0x7E0000 + 0xF50000;
-    address[3] = A_RADIUS_X - 0x7E0000 + 0xF50000;
-    address[4] = A_RADIUS_Y - 0x7E0000 + 0xF50000;
-    address[5] = A_WIDTH - 0x7E0000 + 0xF50000;
-    address[6] = A_GAME_STATE - 0x7E0000 + 0xF50000;
-    address[7] = A_DOOR_STUFF - 0x7E0000 + 0xF50000;
-    sizes[0] = 1;
+fn get_base_wram_value<S : MemorySource>(source : &mut S) -> Result<Vec<u8>, Error> {
+    let mut address : Vec<u32> = vec![0; 31];
+    let mut sizes : Vec<usize> = vec![2;31];
+    address[0] = wram(A_SAMUS_X);
+    address[1] = wram(A_SAMUS_Y);
+    address[2] = wram(A_RADIUS_X);
+    address[3] = wram(A_RADIUS_Y);
+    address[4] = wram(A_WIDTH);
+    address[5] = wram(A_GAME_STATE);
+    address[6] = wram(A_DOOR_STUFF);
+    address[7] = wram(A_HEIGHT);
+    address[8] = wram(A_ROOM_POINTER);
+    address[9] = wram(A_ROOM_STATE);
+    address[10] = wram(A_ENEMIES);
+    address[11] = wram(A_PROJECTILE_X);
+    address[12] = wram(A_PROJECTILE_Y);
+    address[13] = wram(A_PROJECTILE_RADIUS_X);
+    address[14] = wram(A_PROJECTILE_RADIUS_Y);
+    address[15] = wram(A_PROJECTILE_TYPE);
+    address[16] = wram(A_PLM_IDS);
+    address[17] = wram(A_PLM_BLOCKS);
+    address[18] = wram(A_ENEMY_PROJECTILE_IDS);
+    address[19] = wram(A_ENEMY_PROJECTILE_X);
+    address[20] = wram(A_ENEMY_PROJECTILE_Y);
+    address[21] = wram(A_ENEMY_PROJECTILE_RADIUS_X);
+    address[22] = wram(A_ENEMY_PROJECTILE_RADIUS_Y);
+    address[23] = wram(A_SCROLLS);
+    address[24] = wram(A_LAYER1_X);
+    address[25] = wram(A_LAYER1_Y);
+    address[26] = wram(A_SAMUS_SUBPIXEL_X);
+    address[27] = wram(A_SAMUS_SUBPIXEL_Y);
+    address[28] = wram(A_SAMUS_SPEEDS);
+    address[29] = wram(A_SAMUS_POSE);
+    address[30] = wram(A_FRAME_COUNTER);
+    sizes[0] = 2;
     sizes[1] = 2;
     sizes[2] = 2;
     sizes[3] = 2;
     sizes[4] = 2;
-    sizes[5] = 2;
-    sizes[6] = 1;
+    sizes[5] = 1;
+    sizes[6] = 2;
     sizes[7] = 2;
-    usb2snes.get_multi_address_as_u8(address, sizes)
+    sizes[8] = 2;
+    sizes[9] = 2;
+    sizes[10] = ENEMY_SLOTS * ENEMY_SLOT_SIZE;
+    for size in sizes.iter_mut().skip(11).take(5) {
+        *size = PROJECTILE_ARRAY_SIZE;
+    }
+    sizes[16] = PLM_ARRAY_SIZE;
+    sizes[17] = PLM_ARRAY_SIZE;
+    for size in sizes.iter_mut().skip(18).take(5) {
+        *size = ENEMY_PROJECTILE_ARRAY_SIZE;
+    }
+    sizes[23] = SCROLLS_SIZE;
+    sizes[28] = SPEED_BLOCK_SIZE;
+    sizes[29] = POSE_BLOCK_SIZE;
+    source.read_multi(&address, &sizes)
+}
+
+// Read the ROM banks we need once, sources without ROM will just not have them
+fn load_rom_banks<S : MemorySource>(source : &mut S, data : &mut SharedData, banks : &[u8]) -> Result<(), Error> {
+    if !source.has_rom() {
+        return Ok(());
+    }
+    for bank in banks {
+        if data.rom_data.has_bank(*bank) {
+            continue;
+        }
+        let bytes = source.read(lorom_to_pc(((*bank as u32) << 16) + 0x8000), 0x8000)?;
+        data.rom_data.set_bank(*bank, bytes);
+    }
+    Ok(())
 }
 
 // This is dumb, but << give me overflow error
@@ -165,7 +380,7 @@ fn get_uword(byte1 : u8, byte2 : u8) -> u16 {
 }
 
 fn _get_sword(byte1 : u8, byte2 : u8) -> i16 {
-    (byte2 as i16) << 8 + (byte1 as i16)
+    ((byte2 as i16) << 8) + (byte1 as i16)
 }
 
 fn try_to_connect() -> Result<SyncClient, Error> {
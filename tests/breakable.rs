@@ -0,0 +1,66 @@
+use smtileviewer::data::{breakable_label, bts_description, decode_breakable};
+
+// Block type, BTS, glyph drawn and description of every breakable block
+const BLOCKS : &[(u8, u8, &str, &str)] = &[
+    (0xB, 0x00, "C", "1x1 crumble block, respawning"),
+    (0xB, 0x01, "C-", "2x1 crumble block, respawning"),
+    (0xB, 0x02, "C|", "1x2 crumble block, respawning"),
+    (0xB, 0x03, "C+", "2x2 crumble block, respawning"),
+    (0xB, 0x04, "C", "1x1 crumble block, not respawning"),
+    (0xB, 0x05, "C-", "2x1 crumble block, not respawning"),
+    (0xB, 0x06, "C|", "1x2 crumble block, not respawning"),
+    (0xB, 0x07, "C+", "2x2 crumble block, not respawning"),
+    (0xB, 0x0E, ">", "speed booster block, respawning"),
+    (0xB, 0x0F, ">", "speed booster block, not respawning"),
+    (0xC, 0x00, "S", "1x1 shot block, respawning"),
+    (0xC, 0x01, "S-", "2x1 shot block, respawning"),
+    (0xC, 0x02, "S|", "1x2 shot block, respawning"),
+    (0xC, 0x03, "S+", "2x2 shot block, respawning"),
+    (0xC, 0x04, "S", "1x1 shot block, not respawning"),
+    (0xC, 0x05, "S-", "2x1 shot block, not respawning"),
+    (0xC, 0x06, "S|", "1x2 shot block, not respawning"),
+    (0xC, 0x07, "S+", "2x2 shot block, not respawning"),
+    (0xC, 0x0A, "M", "super missile block, respawning"),
+    (0xC, 0x0B, "M", "super missile block, not respawning"),
+    (0xC, 0x0E, "P", "power bomb block, respawning"),
+    (0xC, 0x0F, "P", "power bomb block, not respawning"),
+    (0xE, 0x00, "G", "Grapple block"),
+    (0xE, 0x01, "G", "crumbling grapple block, respawning"),
+    (0xE, 0x02, "G", "crumbling grapple block, not respawning"),
+    (0xF, 0x00, "B", "1x1 bomb block, respawning"),
+    (0xF, 0x01, "B-", "2x1 bomb block, respawning"),
+    (0xF, 0x02, "B|", "1x2 bomb block, respawning"),
+    (0xF, 0x03, "B+", "2x2 bomb block, respawning"),
+    (0xF, 0x04, "B", "1x1 bomb block, not respawning"),
+    (0xF, 0x05, "B-", "2x1 bomb block, not respawning"),
+    (0xF, 0x06, "B|", "1x2 bomb block, not respawning"),
+    (0xF, 0x07, "B+", "2x2 bomb block, not respawning")
+];
+
+#[test]
+fn known_blocks() {
+    for (block_type, bts, label, description) in BLOCKS {
+        assert!(decode_breakable(*block_type, *bts).is_some(), "{:X} ${:02X}", block_type, bts);
+        assert_eq!(breakable_label(*block_type, *bts, "?"), *label, "{:X} ${:02X}", block_type, bts);
+        assert_eq!(bts_description(*block_type, *bts), *description, "{:X} ${:02X}", block_type, bts);
+    }
+}
+
+#[test]
+fn unknown_blocks() {
+    for block_type in [0xBu8, 0xC, 0xE, 0xF] {
+        for bts in 0..=0xFF {
+            if BLOCKS.iter().any(|b| b.0 == block_type && b.1 == bts) {
+                continue;
+            }
+            assert!(decode_breakable(block_type, bts).is_none(), "{:X} ${:02X}", block_type, bts);
+        }
+    }
+    // The whole BTS is shown, blocks with the same low nibble get different labels
+    assert_eq!(breakable_label(0xC, 0x08, "S"), "S08");
+    assert_eq!(breakable_label(0xC, 0x48, "S"), "S48");
+    assert_eq!(breakable_label(0xF, 0x09, "B"), "B09");
+    assert_eq!(breakable_label(0xB, 0x40, "C"), "C40");
+    assert_eq!(bts_description(0xC, 0x48), "Shot block $48");
+    assert_eq!(bts_description(0xE, 0x03), "Grapple $03");
+}
@@ -0,0 +1,30 @@
+use std::sync::Arc;
+use sdl2::rect::Point;
+use smtileviewer::data::SharedData;
+use smtileviewer::render::{game_camera, samus_camera, screen_scroll};
+
+#[test]
+fn screen_scrolls() {
+    // 3x2 screens, red, blue and green
+    let mut data = SharedData::new();
+    data.width = 0x30;
+    data.height = 0x20;
+    data.scrolls = Arc::new(vec![0x02, 0x01, 0x00, 0x01, 0x01, 0x02]);
+    assert_eq!(screen_scroll(&data, 0, 0), Some(0x02));
+    assert_eq!(screen_scroll(&data, 2, 0), Some(0x00));
+    assert_eq!(screen_scroll(&data, 0, 1), Some(0x01));
+    assert_eq!(screen_scroll(&data, 2, 1), Some(0x02));
+    // Outside of the room
+    assert_eq!(screen_scroll(&data, 3, 0), None);
+    assert_eq!(screen_scroll(&data, 0, 2), None);
+}
+
+#[test]
+fn cameras() {
+    // The game screen is in the middle of the game area
+    assert_eq!(game_camera(Point::new(0x0400, 0x0400)), Point::new(0x0400 - 128, 0x0400 - 112));
+    assert_eq!(game_camera(Point::new(0, 0)), Point::new(-128, -112));
+    let mut data = SharedData::new();
+    data.samus_pos = Point::new(0x0480, 0x0488);
+    assert_eq!(samus_camera(&data), Point::new(0x0480 - 256, 0x0488 - 224));
+}
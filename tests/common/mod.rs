@@ -0,0 +1,57 @@
+// Game memory shared by the tests, each test crate only uses some of it
+#![allow(dead_code)]
+
+use smtileviewer::memorysource::{MemoryImage, WRAM_SIZE};
+
+pub const ROOM_POINTER : u16 = 0x91F8;
+
+pub fn set_word(wram : &mut [u8], address : usize, value : u16) {
+    wram[address..address + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+// WRAM of the game playing in the Landing Site, the tests add what they look at
+pub struct GameWram {
+    pub wram : Vec<u8>,
+    width : usize
+}
+
+impl GameWram {
+    // Room size in tiles, the level data is empty
+    pub fn new(width : u16, height : u16) -> GameWram {
+        let mut game = GameWram {
+            wram : vec![0; WRAM_SIZE],
+            width : width as usize
+        };
+        game.word(0x079B, ROOM_POINTER)
+            .byte(0x0998, 0x08)
+            .word(0x07A5, width)
+            .word(0x07A7, height)
+            .word(0x10000, width * height * 2);
+        game
+    }
+    pub fn word(&mut self, address : usize, value : u16) -> &mut GameWram {
+        set_word(&mut self.wram, address, value);
+        self
+    }
+    pub fn byte(&mut self, address : usize, value : u8) -> &mut GameWram {
+        self.wram[address] = value;
+        self
+    }
+    pub fn samus(&mut self, x : u16, y : u16, radius_x : u16, radius_y : u16) -> &mut GameWram {
+        self.word(0x0AF6, x).word(0x0AFA, y).word(0x0AFE, radius_x).word(0x0B00, radius_y)
+    }
+    pub fn layer1(&mut self, x : u16, y : u16) -> &mut GameWram {
+        self.word(0x0911, x).word(0x0915, y)
+    }
+    pub fn frame_counter(&mut self, frame : u16) -> &mut GameWram {
+        self.word(0x05B6, frame)
+    }
+    // Clip word and BTS of the tile at x,y
+    pub fn tile(&mut self, x : usize, y : usize, clip : u16, bts : u8) -> &mut GameWram {
+        let a = y * self.width + x;
+        self.word(0x10002 + a * 2, clip).byte(0x16402 + a, bts)
+    }
+    pub fn image(self) -> MemoryImage {
+        MemoryImage::new(self.wram, vec![])
+    }
+}
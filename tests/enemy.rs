@@ -0,0 +1,44 @@
+use smtileviewer::enemy::{enemy_name, parse_enemies, ENEMY_SLOTS, ENEMY_SLOT_SIZE};
+use smtileviewer::rom::Rom;
+
+mod common;
+use common::set_word;
+
+#[test]
+fn enemy_slots() {
+    let mut table = vec![0u8; ENEMY_SLOTS * ENEMY_SLOT_SIZE];
+    let slot = 2 * ENEMY_SLOT_SIZE;
+    set_word(&mut table, slot, 0xCEBF);
+    set_word(&mut table, slot + 0x02, 0x0500);
+    set_word(&mut table, slot + 0x06, 0x0490);
+    set_word(&mut table, slot + 0x0A, 0x0008);
+    set_word(&mut table, slot + 0x0C, 0x000C);
+    set_word(&mut table, slot + 0x0E, 0x0500);
+    set_word(&mut table, slot + 0x14, 20);
+    // The last slot is used too, the others are free
+    set_word(&mut table, (ENEMY_SLOTS - 1) * ENEMY_SLOT_SIZE, 0xD07F);
+    let enemies = parse_enemies(&table);
+    assert_eq!(enemies.len(), 2);
+    let enemy = &enemies[0];
+    assert_eq!((enemy.slot, enemy.id, enemy.x, enemy.y), (2, 0xCEBF, 0x0500, 0x0490));
+    assert_eq!((enemy.radius_x, enemy.radius_y, enemy.health), (8, 12, 20));
+    assert!(enemy.intangible() && enemy.invisible());
+    assert_eq!((enemies[1].slot, enemies[1].id), (ENEMY_SLOTS - 1, 0xD07F));
+    assert!(!enemies[1].intangible() && !enemies[1].invisible());
+}
+
+#[test]
+fn enemy_names() {
+    let mut bank_a0 = vec![0u8; 0x8000];
+    set_word(&mut bank_a0, (0xCEBF + 0x3E) & 0x7FFF, 0x8000);
+    set_word(&mut bank_a0, (0xD07F + 0x3E) & 0x7FFF, 0x0000);
+    let mut bank_b4 = vec![0u8; 0x8000];
+    bank_b4[..10].copy_from_slice(b"SKREE     ");
+    let mut rom = Rom::new();
+    rom.set_bank(0xA0, bank_a0);
+    rom.set_bank(0xB4, bank_b4);
+    assert_eq!(enemy_name(&rom, 0xCEBF), "SKREE");
+    // No debug name, or no ROM, the id is shown
+    assert_eq!(enemy_name(&rom, 0xD07F), "Enemy $D07F");
+    assert_eq!(enemy_name(&Rom::new(), 0xCEBF), "Enemy $CEBF");
+}
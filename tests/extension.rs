@@ -0,0 +1,61 @@
+use smtileviewer::render::extension_parent;
+
+const WIDTH : usize = 32;
+
+fn level() -> Vec<u8> {
+    vec![0; 0x10000]
+}
+
+// Block type in the high nibble of the clip word, BTS is the signed offset for extensions
+fn set_block(map_infos : &mut [u8], tile : usize, block_type : u8, bts : i8) {
+    map_infos[0x0002 + tile * 2 + 1] = block_type << 4;
+    map_infos[0x6402 + tile] = bts as u8;
+}
+
+#[test]
+fn blocks_have_no_parent() {
+    let mut map_infos = level();
+    set_block(&mut map_infos, 40, 0x8, 0);
+    assert_eq!(extension_parent(&map_infos, WIDTH, 40), None);
+    assert_eq!(extension_parent(&map_infos, WIDTH, 41), None);
+}
+
+#[test]
+fn horizontal_chain() {
+    let mut map_infos = level();
+    set_block(&mut map_infos, 40, 0xB, 0x02);
+    set_block(&mut map_infos, 41, 0x5, -1);
+    set_block(&mut map_infos, 42, 0x5, -1);
+    assert_eq!(extension_parent(&map_infos, WIDTH, 41), Some(40));
+    assert_eq!(extension_parent(&map_infos, WIDTH, 42), Some(40));
+    // Extensions can point forward too
+    set_block(&mut map_infos, 39, 0x5, 1);
+    assert_eq!(extension_parent(&map_infos, WIDTH, 39), Some(40));
+}
+
+#[test]
+fn vertical_then_horizontal() {
+    let mut map_infos = level();
+    set_block(&mut map_infos, 40, 0xF, 0x05);
+    set_block(&mut map_infos, 41, 0x5, -1);
+    set_block(&mut map_infos, 40 + WIDTH, 0xD, -1);
+    set_block(&mut map_infos, 41 + WIDTH, 0xD, -1);
+    set_block(&mut map_infos, 41 + WIDTH * 2, 0xD, -1);
+    assert_eq!(extension_parent(&map_infos, WIDTH, 40 + WIDTH), Some(40));
+    assert_eq!(extension_parent(&map_infos, WIDTH, 41 + WIDTH * 2), Some(40));
+}
+
+#[test]
+fn broken_chains() {
+    let mut map_infos = level();
+    // Zero offset
+    set_block(&mut map_infos, 40, 0x5, 0);
+    assert_eq!(extension_parent(&map_infos, WIDTH, 40), None);
+    // Out of the level data
+    set_block(&mut map_infos, 0, 0xD, -1);
+    assert_eq!(extension_parent(&map_infos, WIDTH, 0), None);
+    // Two extensions pointing at each other
+    set_block(&mut map_infos, 50, 0x5, 1);
+    set_block(&mut map_infos, 51, 0x5, -1);
+    assert_eq!(extension_parent(&map_infos, WIDTH, 50), None);
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use smtileviewer::data::SharedData;
+use smtileviewer::snapshot::FrameChannel;
+
+#[test]
+fn dropped_frames() {
+    let mut data = SharedData::new();
+    assert!(data.set_frame_counter(100));
+    assert_eq!(data.dropped_frames, 0);
+    // Same frame twice
+    assert!(!data.set_frame_counter(100));
+    assert!(data.set_frame_counter(101));
+    assert_eq!(data.dropped_frames, 0);
+    // 102 and 103 were never read
+    assert!(data.set_frame_counter(104));
+    assert_eq!(data.dropped_frames, 2);
+    // The counter wraps
+    data.set_frame_counter(0xFFFF);
+    let dropped = data.dropped_frames;
+    assert!(data.set_frame_counter(1));
+    assert_eq!(data.dropped_frames, dropped + 1);
+    // Going back is a loaded state, nothing was dropped
+    data.set_frame_counter(500);
+    let dropped = data.dropped_frames;
+    assert!(data.set_frame_counter(50));
+    assert_eq!(data.dropped_frames, dropped);
+}
+
+#[test]
+fn published_snapshots() {
+    let channel = FrameChannel::new(SharedData::new());
+    assert_eq!(channel.latest().version, 0);
+    let mut data = SharedData::new();
+    data.map_data = Arc::new(vec![0; 0x10000]);
+    data.set_frame_counter(100);
+    channel.publish(&mut data);
+    let first = channel.latest();
+    assert_eq!((first.version, first.frame_counter), (1, Some(100)));
+    // The snapshot shares the level data, overlays and trail instead of copying them
+    assert!(Arc::ptr_eq(&first.map_data, &data.map_data));
+    assert!(Arc::ptr_eq(&first.enemies, &data.enemies) && Arc::ptr_eq(&first.trail, &data.trail));
+    data.set_frame_counter(101);
+    Arc::make_mut(&mut data.map_data)[2] = 0x23;
+    data.push_trail();
+    channel.publish(&mut data);
+    // A snapshot already taken does not change
+    assert_eq!((first.version, first.frame_counter, first.map_data[2], first.trail.len()), (1, Some(100), 0, 0));
+    let second = channel.latest();
+    assert_eq!((second.version, second.frame_counter, second.map_data[2], second.trail.len()), (2, Some(101), 0x23, 1));
+}
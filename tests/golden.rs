@@ -0,0 +1,64 @@
+use sdl2::image::{LoadSurface, SaveSurface};
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::rect::Rect;
+use sdl2::render::BlendMode;
+use sdl2::surface::Surface;
+use smtileviewer::render;
+
+mod common;
+use common::GameWram;
+
+const GOLDEN : &str = "tests/golden/game_area.png";
+
+// A room of solid blocks, slopes and extensions, nothing that is drawn with text
+fn room_wram() -> Vec<u8> {
+    let width = 0x20;
+    let mut game = GameWram::new(width as u16, 0x1C);
+    game.samus(0x0100, 0x0150, 0x0007, 0x0015).layer1(0x0080, 0x0060).frame_counter(1000);
+    for x in 0..width {
+        game.tile(x, 0x17, 0x8000, 0).tile(x, 0x18, 0x8000, 0);
+    }
+    for y in 0..0x17 {
+        game.tile(0, y, 0x8000, 0);
+    }
+    // Slopes of every shape kind, with their flips
+    for (x, bts) in [0x00u8, 0x01, 0x07, 0x12, 0x52, 0x92, 0xD2, 0x13, 0x14, 0x15, 0x16, 0x1B, 0x1C, 0x1D].iter().enumerate() {
+        game.tile(2 + x, 0x16, 0x1000, *bts);
+    }
+    // A 2x2 block made of extensions of its top left tile
+    game.tile(20, 0x12, 0x8000, 0).tile(21, 0x12, 0x5000, 0xFF).tile(20, 0x13, 0xD000, 0xFF).tile(21, 0x13, 0xD000, 0xFF);
+    game.wram
+}
+
+// Part of the canvas between the borders, the text around it depends on the font rendering
+fn game_area(png : &str) -> Surface<'static> {
+    let mut image = Surface::from_file(png).unwrap().convert_format(PixelFormatEnum::RGBA32).unwrap();
+    image.set_blend_mode(BlendMode::None).unwrap();
+    let mut area = Surface::new(500, 427, PixelFormatEnum::RGBA32).unwrap();
+    let source = if image.width() == area.width() { None } else { Some(Rect::new(18, 68, 500, 427)) };
+    image.blit(source, &mut area, None).unwrap();
+    area
+}
+
+fn pixels(surface : &Surface) -> Vec<u8> {
+    surface.with_lock(|pixels| pixels.to_vec())
+}
+
+// UPDATE_GOLDEN=1 cargo test --test golden writes the expected image again
+#[test]
+fn render_matches_the_golden_image() {
+    let directory = std::env::temp_dir();
+    let wram_file = directory.join(format!("smtv_golden_{}.bin", std::process::id()));
+    let png_file = directory.join(format!("smtv_golden_{}.png", std::process::id()));
+    std::fs::write(&wram_file, room_wram()).unwrap();
+    render::render_to_png(wram_file.to_str().unwrap(), None, png_file.to_str().unwrap()).unwrap();
+    let rendered = game_area(png_file.to_str().unwrap());
+    std::fs::remove_file(&wram_file).unwrap();
+    std::fs::remove_file(&png_file).unwrap();
+    if std::env::var("UPDATE_GOLDEN").is_ok() {
+        rendered.save(GOLDEN).unwrap();
+    }
+    let expected = game_area(GOLDEN);
+    let different = pixels(&rendered).chunks(4).zip(pixels(&expected).chunks(4)).filter(|(a, b)| a != b).count();
+    assert_eq!(different, 0, "{} pixels differ from {}", different, GOLDEN);
+}
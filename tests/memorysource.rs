@@ -0,0 +1,37 @@
+use smtileviewer::memorysource::{MemoryImage, MemorySource, WRAM_BASE, WRAM_SIZE, vget_requests};
+use smtileviewer::mockserver::{MockClient, MockServer};
+use tungstenite::Message;
+
+#[test]
+fn vget_pieces() {
+    let requests = vget_requests(&[0xF50000, 0xF51000, 0xF52000], &[2, 0x200, 0]);
+    assert_eq!(requests, vec![(vec![0xF50000, 0xF51000, 0xF510FF, 0xF511FE], vec![2, 255, 255, 2])]);
+    // Nine blocks make a second request
+    let addresses : Vec<u32> = (0..9).map(|i| 0xF50000 + i * 0x10).collect();
+    let requests = vget_requests(&addresses, &[4; 9]);
+    assert_eq!(requests.len(), 2);
+    assert_eq!((requests[0].0.len(), &requests[1].0), (8, &vec![0xF50080]));
+    // A big block fills whole requests
+    let requests = vget_requests(&[0xF50000], &[0x800]);
+    assert_eq!(requests.iter().map(|r| r.1.len()).collect::<Vec<usize>>(), vec![8, 1]);
+    assert_eq!(requests.iter().flat_map(|r| r.1.iter()).sum::<usize>(), 0x800);
+    assert_eq!(requests[1], (vec![0xF50000 + 8 * 255], vec![8]));
+}
+
+#[test]
+fn mock_reads_like_the_sd2snes() {
+    let wram : Vec<u8> = (0..WRAM_SIZE).map(|i| (i % 251) as u8).collect();
+    let server = MockServer::start(MemoryImage::new(wram.clone(), vec![])).expect("Can't start the mock server");
+    let mut client = MockClient::connect(server.address()).unwrap();
+    // Split in VGETs the server accepts
+    let data = client.read_multi(&[WRAM_BASE + 0x10, WRAM_BASE + 0x1000], &[0x800, 3]).unwrap();
+    assert_eq!(&data[..0x800], &wram[0x10..0x810]);
+    assert_eq!(&data[0x800..], &wram[0x1000..0x1003]);
+    // A single block can be as big as we want
+    assert_eq!(client.read(WRAM_BASE, 0x10000).unwrap(), &wram[..0x10000]);
+    // The server drops a client sending a VGET with a block over 255 bytes
+    let (mut ws, _) = tungstenite::connect(format!("ws://{}", server.address())).unwrap();
+    let query = r#"{"Opcode":"GetAddress","Space":"SNES","Flags":[],"Operands":["f50000","2","f50002","100"]}"#;
+    ws.send(Message::text(query)).unwrap();
+    assert!(!matches!(ws.read(), Ok(Message::Binary(_))));
+}
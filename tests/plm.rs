@@ -0,0 +1,27 @@
+use smtileviewer::plm::{parse_plms, plm_kind, PLM_ARRAY_SIZE, PLM_SLOTS};
+
+mod common;
+use common::set_word;
+
+#[test]
+fn plm_slots() {
+    let (mut ids, mut blocks) = (vec![0u8; PLM_ARRAY_SIZE], vec![0u8; PLM_ARRAY_SIZE]);
+    // An item on the tile 5,1 of a 0x90 tiles wide room and a door in the last slot
+    set_word(&mut ids, 2 * 2, 0xEED7);
+    set_word(&mut blocks, 2 * 2, (0x90 + 5) * 2);
+    set_word(&mut ids, (PLM_SLOTS - 1) * 2, 0xC842);
+    set_word(&mut blocks, (PLM_SLOTS - 1) * 2, 0x20);
+    let plms = parse_plms(&ids, &blocks);
+    assert_eq!(plms.len(), 2);
+    assert_eq!((plms[0].slot, plms[0].id, plms[0].tile_index()), (2, 0xEED7, 0x90 + 5));
+    assert_eq!((plms[1].slot, plms[1].id, plms[1].tile_index()), (PLM_SLOTS - 1, 0xC842, 0x10));
+}
+
+#[test]
+fn plm_kinds() {
+    let kinds = [(0xB63B, "Scroll"), (0xB703, "Scroll"), (0xC794, "Grey door"), (0xC842, "Door"), (0xEED7, "Item"),
+        (0xEF2B, "Chozo item"), (0xEF7F, "Hidden item"), (0xB704, "PLM"), (0xEF28, "PLM")];
+    for (id, kind) in kinds {
+        assert_eq!(plm_kind(id), kind, "${:04X}", id);
+    }
+}
@@ -0,0 +1,52 @@
+use smtileviewer::projectile::{parse_projectiles, parse_enemy_projectiles, ProjectileKind};
+use smtileviewer::projectile::{PROJECTILE_ARRAY_SIZE, ENEMY_PROJECTILE_ARRAY_SIZE, ENEMY_PROJECTILE_SLOTS};
+
+mod common;
+use common::set_word;
+
+#[test]
+fn samus_projectiles() {
+    let mut arrays = vec![vec![0u8; PROJECTILE_ARRAY_SIZE]; 5];
+    // A missile in the second slot and a bomb in the sixth
+    for (array, value) in [0x04A0u16, 0x0480, 0x0008, 0x0004, 0x0100].iter().enumerate() {
+        set_word(&mut arrays[array], 2, *value);
+    }
+    for (array, value) in [0x0300u16, 0x0200, 0x0004, 0x0004, 0x0500].iter().enumerate() {
+        set_word(&mut arrays[array], 5 * 2, *value);
+    }
+    let projectiles = parse_projectiles(&arrays[0], &arrays[1], &arrays[2], &arrays[3], &arrays[4]);
+    assert_eq!(projectiles.len(), 2);
+    let missile = &projectiles[0];
+    assert_eq!((missile.slot, missile.x, missile.y, missile.radius_x, missile.radius_y), (1, 0x04A0, 0x0480, 8, 4));
+    assert_eq!((missile.kind(), missile.label().as_str()), (ProjectileKind::Missile, "M"));
+    assert_eq!((projectiles[1].slot, projectiles[1].kind()), (5, ProjectileKind::Bomb));
+}
+
+#[test]
+fn projectile_labels() {
+    let mut arrays = vec![vec![0u8; PROJECTILE_ARRAY_SIZE]; 5];
+    let types = [0x0000u16, 0x0013, 0x001F, 0x0200, 0x0300, 0x0500, 0x0800];
+    for (slot, projectile_type) in types.iter().enumerate() {
+        set_word(&mut arrays[2], slot * 2, 4);
+        set_word(&mut arrays[4], slot * 2, *projectile_type);
+    }
+    let projectiles = parse_projectiles(&arrays[0], &arrays[1], &arrays[2], &arrays[3], &arrays[4]);
+    let labels : Vec<String> = projectiles.iter().map(|p| p.label()).collect();
+    assert_eq!(labels, vec!["B", "CWI", "CWISP", "SM", "PB", "b", "0800"]);
+    assert_eq!(projectiles[6].kind(), ProjectileKind::Other);
+}
+
+#[test]
+fn enemy_projectiles() {
+    let mut arrays = vec![vec![0u8; ENEMY_PROJECTILE_ARRAY_SIZE]; 5];
+    // An enemy shot in the first and in the last slot
+    for (array, value) in [0x8BC2u16, 0x0520, 0x0498, 0x0004, 0x0006].iter().enumerate() {
+        set_word(&mut arrays[array], 0, *value);
+    }
+    set_word(&mut arrays[0], (ENEMY_PROJECTILE_SLOTS - 1) * 2, 0x9E90);
+    let shots = parse_enemy_projectiles(&arrays[0], &arrays[1], &arrays[2], &arrays[3], &arrays[4]);
+    assert_eq!(shots.len(), 2);
+    let shot = &shots[0];
+    assert_eq!((shot.slot, shot.id, shot.x, shot.y, shot.radius_x, shot.radius_y), (0, 0x8BC2, 0x0520, 0x0498, 4, 6));
+    assert_eq!((shots[1].slot, shots[1].id), (ENEMY_PROJECTILE_SLOTS - 1, 0x9E90));
+}
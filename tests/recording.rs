@@ -0,0 +1,132 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use smtileviewer::data::SharedData;
+use smtileviewer::memorysource::MemoryImage;
+use smtileviewer::recording::{Recorder, RecordingSource, ReplayControl, ReplaySource};
+use smtileviewer::wsthread::actually_getting_data;
+
+mod common;
+use common::{GameWram, set_word};
+
+// Samus X is the number of the frame to know where the replay is
+fn game_image() -> MemoryImage {
+    GameWram::new(0x30, 0x20).image()
+}
+
+fn set_frame(image : &mut MemoryImage, frame : u16) {
+    set_word(&mut image.wram, 0x05B6, 1000 + frame);
+    set_word(&mut image.wram, 0x0AF6, frame);
+}
+
+fn record_image(path : &str, mut image : MemoryImage, frames : u16, level_refresh : u16) -> Recorder {
+    let mut recorder = Recorder::new(path).unwrap();
+    let mut data = SharedData::new();
+    data.level_refresh = level_refresh;
+    for frame in 0..frames {
+        set_frame(&mut image, frame);
+        actually_getting_data(&mut RecordingSource::new(&mut image, &mut recorder), &mut data).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    recorder
+}
+
+fn record(path : &str, frames : u16, level_refresh : u16) {
+    record_image(path, game_image(), frames, level_refresh);
+}
+
+// Poll the replay once and give the frame it shows
+fn replayed_frame(replay : &mut ReplaySource, data : &mut SharedData) -> i32 {
+    actually_getting_data(replay, data).unwrap();
+    data.samus_pos.x
+}
+
+fn replay(path : &str) -> (ReplaySource, Arc<Mutex<ReplayControl>>) {
+    let control = Arc::new(Mutex::new(ReplayControl::new()));
+    control.lock().unwrap().paused = true;
+    let replay = ReplaySource::load(path, vec![], Arc::clone(&control)).unwrap();
+    (replay, control)
+}
+
+#[test]
+fn record_and_replay() {
+    let path = std::env::temp_dir().join(format!("smtv_replay_{}.smtv", std::process::id()));
+    let path = path.to_str().unwrap();
+    record(path, 6, 0);
+    let (mut replay, control) = replay(path);
+    assert_eq!(replay.frame_count(), 6);
+    assert!(control.lock().unwrap().length >= 25);
+    let mut data = SharedData::new();
+    data.level_refresh = 0;
+    assert_eq!(replayed_frame(&mut replay, &mut data), 0);
+    // Paused, it stays on the same frame
+    assert_eq!(replayed_frame(&mut replay, &mut data), 0);
+    assert_eq!(data.map_data.len(), 0x10000);
+    control.lock().unwrap().step = 1;
+    assert_eq!(replayed_frame(&mut replay, &mut data), 1);
+    control.lock().unwrap().step = 2;
+    assert_eq!(replayed_frame(&mut replay, &mut data), 3);
+    control.lock().unwrap().step = -1;
+    assert_eq!(replayed_frame(&mut replay, &mut data), 2);
+    // Stepping stops at the last frame
+    control.lock().unwrap().step = 100;
+    assert_eq!(replayed_frame(&mut replay, &mut data), 5);
+    // Seeking goes back from the start of the recording
+    control.lock().unwrap().seek = -60000;
+    assert_eq!(replayed_frame(&mut replay, &mut data), 0);
+    assert_eq!(control.lock().unwrap().position, 0);
+    control.lock().unwrap().seek = 60000;
+    assert_eq!(replayed_frame(&mut replay, &mut data), 5);
+    let control = control.lock().unwrap();
+    assert_eq!(control.position, control.length);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn level_refresh_is_not_a_frame() {
+    let path = std::env::temp_dir().join(format!("smtv_refresh_{}.smtv", std::process::id()));
+    let path = path.to_str().unwrap();
+    // The visible rows are read again on every frame
+    record(path, 4, 1);
+    let (mut replay, control) = replay(path);
+    assert_eq!(replay.frame_count(), 4);
+    let mut data = SharedData::new();
+    assert_eq!(replayed_frame(&mut replay, &mut data), 0);
+    for frame in 1..4 {
+        control.lock().unwrap().step = 1;
+        assert_eq!(replayed_frame(&mut replay, &mut data), frame);
+    }
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn rom_comes_from_the_replay() {
+    let path = std::env::temp_dir().join(format!("smtv_rom_{}.smtv", std::process::id()));
+    let path = path.to_str().unwrap();
+    let rom : Vec<u8> = (0..0x200000).map(|i| (i / 0x8000) as u8).collect();
+    let mut image = game_image();
+    image.rom = rom.clone();
+    record_image(path, image, 3, 0);
+    // The room banks read from the cartridge are left out and the level data compresses
+    assert!(std::fs::metadata(path).unwrap().len() < 0x4000);
+    let control = Arc::new(Mutex::new(ReplayControl::new()));
+    let mut replay = ReplaySource::load(path, rom, Arc::clone(&control)).unwrap();
+    let mut data = SharedData::new();
+    actually_getting_data(&mut replay, &mut data).unwrap();
+    assert!(data.rom_data.has_bank(0x8F));
+    let mut replay = ReplaySource::load(path, vec![], control).unwrap();
+    let mut data = SharedData::new();
+    actually_getting_data(&mut replay, &mut data).unwrap();
+    assert!(!data.rom_data.has_bank(0x8F));
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn killed_recording() {
+    let path = std::env::temp_dir().join(format!("smtv_killed_{}.smtv", std::process::id()));
+    let path = path.to_str().unwrap();
+    // The recorder is never dropped when the viewer is killed, the records after the last flush are lost
+    std::mem::forget(record_image(path, game_image(), 70, 0));
+    let (replay, _control) = replay(path);
+    assert!(replay.frame_count() >= 50 && replay.frame_count() < 70, "{} frames", replay.frame_count());
+    std::fs::remove_file(path).unwrap();
+}
@@ -0,0 +1,106 @@
+use smtileviewer::rom::Rom;
+use smtileviewer::room::{self, parse_room_header, room_description, door_description, room_name};
+
+fn set_word(bank : &mut [u8], address : u16, value : u16) {
+    let offset = (address & 0x7FFF) as usize;
+    bank[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn set_bytes(bank : &mut [u8], address : u16, bytes : &[u8]) {
+    let offset = (address & 0x7FFF) as usize;
+    bank[offset..offset + bytes.len()].copy_from_slice(bytes);
+}
+
+// Landing Site header with its four states and four doors, the state data follows the state list
+// and the door list follows the state data like in the game
+fn landing_site() -> Rom {
+    let mut bank_8f = vec![0u8; 0x8000];
+    set_bytes(&mut bank_8f, 0x91F8, &[0x00, 0x00, 0x17, 0x00, 0x09, 0x05, 0x70, 0xA0, 0x00, 0x7B, 0x92]);
+    // Event 0E, power bombs, event 00 then the default state
+    set_bytes(&mut bank_8f, 0x9203, &[0x12, 0xE6, 0x0E, 0x61, 0x92, 0x69, 0xE6, 0x47, 0x92, 0x12, 0xE6, 0x00, 0x2D, 0x92, 0xE6, 0xE5]);
+    for (i, state) in [0x9213u16, 0x922D, 0x9247, 0x9261].iter().enumerate() {
+        set_bytes(&mut bank_8f, *state, &[0xBB, 0xC2, 0xC2, 0x00, 0x06, 0x05]);
+        set_word(&mut bank_8f, state + 8, 0x883D + i as u16);
+        set_word(&mut bank_8f, state + 20, 0x8000 + i as u16);
+        set_word(&mut bank_8f, state + 24, 0x91F0);
+    }
+    for (i, door) in [0x8916u16, 0x8922, 0x892E, 0x893A].iter().enumerate() {
+        set_word(&mut bank_8f, 0x927B + i as u16 * 2, *door);
+    }
+    // First thing after the list, not a door
+    set_word(&mut bank_8f, 0x9283, 0x0000);
+    let mut bank_83 = vec![0u8; 0x8000];
+    set_bytes(&mut bank_83, 0x8916, &[0xFD, 0x92, 0x00, 0x05, 0x3E, 0x06, 0x03, 0x00, 0x00, 0x80, 0x00, 0x00]);
+    set_bytes(&mut bank_83, 0x8922, &[0xB3, 0x92, 0x00, 0x04, 0x01, 0x26, 0x00, 0x02, 0x00, 0x80, 0x00, 0x00]);
+    set_bytes(&mut bank_83, 0x892E, &[0xD5, 0x93, 0x40, 0x00, 0x8E, 0x46, 0x08, 0x04, 0x00, 0x80, 0x6F, 0xB9]);
+    set_bytes(&mut bank_83, 0x893A, &[0x2C, 0xE8, 0x80, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00]);
+    let mut rom = Rom::new();
+    rom.set_bank(0x8F, bank_8f);
+    rom.set_bank(0x83, bank_83);
+    rom
+}
+
+#[test]
+fn landing_site_header() {
+    let rom = landing_site();
+    let room = parse_room_header(&rom, 0x91F8, 0x9213).unwrap();
+    assert_eq!((room.index, room.area, room.map_x, room.map_y), (0x00, 0x00, 0x17, 0x00));
+    assert_eq!((room.width, room.height, room.up_scroller, room.down_scroller), (9, 5, 0x70, 0xA0));
+    assert_eq!(room.door_list, 0x927B);
+    let state = room.state.as_ref().unwrap();
+    assert_eq!((state.pointer, state.level_data, state.tileset, state.music_data, state.music_track), (0x9213, 0xC2C2BB, 0x00, 0x06, 0x05));
+    assert_eq!((state.enemy_population, state.plm_population, state.setup_asm), (0x883D, 0x8000, 0x91F0));
+    assert_eq!(room_description(&room), "Landing Site - Crateria (23,0) - 9x5 screens - Default state $9213");
+    // The description names the condition of the state the game uses
+    let room = parse_room_header(&rom, 0x91F8, 0x9247).unwrap();
+    assert_eq!(room.state.as_ref().unwrap().enemy_population, 0x883F);
+    assert!(room_description(&room).ends_with("Power bombs state $9247"));
+}
+
+#[test]
+fn state_conditions() {
+    let rom = landing_site();
+    let room = parse_room_header(&rom, 0x91F8, 0x9213).unwrap();
+    assert_eq!(room.states, vec![(0xE612, 0x9261), (0xE669, 0x9247), (0xE612, 0x922D), (0xE5E6, 0x9213)]);
+    let names : Vec<&str> = room.states.iter().map(|s| room::condition_name(s.0)).collect();
+    assert_eq!(names, vec!["Event", "Power bombs", "Event", "Default"]);
+    // A condition we don't know the argument size of stops the parsing
+    let mut broken = landing_site();
+    let mut bank_8f = vec![0u8; 0x8000];
+    set_bytes(&mut bank_8f, 0x91F8, &[0x00, 0x00, 0x17, 0x00, 0x09, 0x05, 0x70, 0xA0, 0x00, 0x7B, 0x92, 0x34, 0x12]);
+    broken.set_bank(0x8F, bank_8f);
+    assert!(parse_room_header(&broken, 0x91F8, 0x9213).is_none());
+}
+
+#[test]
+fn doors() {
+    let rom = landing_site();
+    let room = parse_room_header(&rom, 0x91F8, 0x9213).unwrap();
+    assert_eq!(room.doors.len(), 4);
+    let pointers : Vec<u16> = room.doors.iter().map(|d| d.pointer).collect();
+    assert_eq!(pointers, vec![0x8916, 0x8922, 0x892E, 0x893A]);
+    let door = &room.doors[2];
+    assert_eq!((door.destination, door.flags, door.direction), (0x93D5, 0x40, 0x00));
+    assert_eq!((door.cap_x, door.cap_y, door.screen_x, door.screen_y, door.spawn_distance, door.asm), (0x8E, 0x46, 0x08, 0x04, 0x8000, 0xB96F));
+    assert_eq!(door_description(0, &room.doors[0]), vec![
+        "Door 0 - $83:8916", "To : Parlor and Alcatraz ($92FD)", "Direction : Left, closes behind", "Cap : 62,6",
+        "Screen : 3,0", "Spawn distance : $8000", "No door ASM"]);
+    let lines = door_description(2, door);
+    assert!(lines.contains(&String::from("Changes area")));
+    assert!(lines.contains(&String::from("Door ASM : $8F:B96F")));
+    // Rooms missing from the names are shown with their pointer
+    let lines = door_description(3, &room.doors[3]);
+    assert_eq!(&lines[1..4], &["To : Room $E82C", "Direction : Up", "Elevator"]);
+}
+
+#[test]
+fn room_names() {
+    assert_eq!(room_name(0x91F8), Some("Landing Site"));
+    assert_eq!(room_name(0xC98E), Some("Bowling Alley"));
+    assert_eq!(room_name(0xCA08), Some("Wrecked Ship Entrance"));
+    assert_eq!(room_name(0xCA52), Some("Attic"));
+    assert_eq!(room_name(0xCD13), Some("Phantoon's Room"));
+    assert_eq!(room_name(0xCFC9), Some("Main Street"));
+    assert_eq!(room_name(0xDA60), Some("Draygon's Room"));
+    assert_eq!(room_name(0xE82C), None);
+}
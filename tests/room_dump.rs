@@ -0,0 +1,50 @@
+use sdl2::image::LoadSurface;
+use sdl2::surface::Surface;
+use smtileviewer::data::SharedData;
+use smtileviewer::render;
+use smtileviewer::wsthread::actually_getting_data;
+
+mod common;
+use common::GameWram;
+
+// A 4x3 tiles room with a floor, a slope and a door
+fn small_room() -> GameWram {
+    let mut game = GameWram::new(4, 3);
+    game.samus(0x0020, 0x0010, 0x0007, 0x0015);
+    for x in 0..4 {
+        game.tile(x, 2, 0x8000, 0);
+    }
+    game.tile(3, 1, 0x1012, 0x40).tile(0, 1, 0x9000, 0x02);
+    game
+}
+
+#[test]
+fn dump() {
+    let mut data = SharedData::new();
+    actually_getting_data(&mut small_room().image(), &mut data).unwrap();
+    let dump = render::room_dump(&data);
+    let lines : Vec<&str> = dump.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "# 4 x 3 tiles - clip:BTS");
+    assert_eq!(lines[1], "0000:00 0000:00 0000:00 0000:00");
+    assert_eq!(lines[2], "9000:02 0000:00 0000:00 1012:40");
+    assert_eq!(lines[3], "8000:00 8000:00 8000:00 8000:00");
+}
+
+#[test]
+fn export() {
+    let directory = std::env::temp_dir();
+    let wram_file = directory.join(format!("smtv_room_{}.bin", std::process::id()));
+    let png_file = directory.join(format!("smtv_room_{}.png", std::process::id()));
+    let dump_file = directory.join(format!("smtv_room_{}.txt", std::process::id()));
+    std::fs::write(&wram_file, small_room().wram).unwrap();
+    render::export_room_from_wram(wram_file.to_str().unwrap(), None, png_file.to_str().unwrap(), Some(dump_file.to_str().unwrap())).unwrap();
+    // One 16x16 tile per block
+    let image = Surface::from_file(&png_file).unwrap();
+    assert_eq!((image.width(), image.height()), (4 * 16, 3 * 16));
+    let dump = std::fs::read_to_string(&dump_file).unwrap();
+    assert!(dump.starts_with("# 4 x 3 tiles") && dump.contains("9000:02"));
+    for file in [wram_file, png_file, dump_file] {
+        std::fs::remove_file(file).unwrap();
+    }
+}
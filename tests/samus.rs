@@ -0,0 +1,60 @@
+use sdl2::rect::Point;
+use smtileviewer::data::{movement_type_color, movement_type_name, pose_name, SharedData, TRAIL_LENGTH, AQUA, ORANGE, PINK};
+use smtileviewer::render;
+use smtileviewer::samus::{direction_name, format_subpixel, parse_movement, POSE_BLOCK_SIZE, SPEED_BLOCK_SIZE};
+
+mod common;
+use common::set_word;
+
+#[test]
+fn speeds_and_pose() {
+    let mut speeds = vec![0u8; SPEED_BLOCK_SIZE];
+    set_word(&mut speeds, 0x00, 0x8000);
+    set_word(&mut speeds, 0x02, 0x0004);
+    set_word(&mut speeds, 0x16, 0x0002);
+    set_word(&mut speeds, 0x18, 0x1000);
+    set_word(&mut speeds, 0x1A, 0x0001);
+    set_word(&mut speeds, 0x1C, 0x4000);
+    let mut pose = vec![0u8; POSE_BLOCK_SIZE];
+    set_word(&mut pose, 0, 0x0009);
+    pose[2] = 0x08;
+    pose[3] = 0x01;
+    let movement = parse_movement(&[0x00, 0x1A], &[0xFF, 0xFF], &speeds, &pose);
+    assert_eq!((movement.subpixel_x, movement.subpixel_y, movement.pose), (0x1A00, 0xFFFF, 0x0009));
+    assert_eq!((movement.speed_x, movement.subspeed_x, movement.momentum_x, movement.submomentum_x), (2, 0x1000, 1, 0x4000));
+    assert_eq!((movement.speed_y, movement.subspeed_y), (4, 0x8000));
+    assert_eq!((movement.direction, movement.movement_type), (0x08, 0x01));
+    assert_eq!(format_subpixel(0x0480, 0x1A00), "1152.1A00");
+    assert_eq!(format_subpixel(4, 0x0008), "4.0008");
+    assert_eq!((direction_name(0x04), direction_name(0x08), direction_name(0x00)), ("left", "right", "forward"));
+}
+
+#[test]
+fn pose_names() {
+    // Both facing directions share the name
+    assert_eq!((pose_name(0x09), pose_name(0x0A)), ("Running", "Running"));
+    assert_eq!((pose_name(0x19), pose_name(0x1A)), ("Spin jump", "Spin jump"));
+    assert_eq!((pose_name(0x00), pose_name(0x9B)), ("Facing forward", "Facing forward"));
+    assert_eq!(pose_name(0xCD), "Shinespark diagonal");
+    assert_eq!(pose_name(0x21), "Unknown pose");
+    assert_eq!(movement_type_name(0x03), "Spin jumping");
+    assert_eq!(movement_type_name(0x07), "Movement $07");
+    assert_eq!((movement_type_color(0x1B), movement_type_color(0x04), movement_type_color(0x07)), (ORANGE, PINK, AQUA));
+    let mut data = SharedData::new();
+    data.movement = parse_movement(&[0, 0], &[0, 0], &[0; SPEED_BLOCK_SIZE], &[0x09, 0x00, 0x08, 0x01]);
+    assert!(render::status_lines(&data, false).iter().any(|line| line.ends_with("Pose $09 Running - Running facing right")));
+}
+
+#[test]
+fn trail() {
+    let mut data = SharedData::new();
+    for x in 0..TRAIL_LENGTH as i32 + 10 {
+        data.samus_pos = Point::new(x, 0x0488);
+        data.radius = Point::new(7, 0x15);
+        data.push_trail();
+    }
+    // Oldest first, the first positions are dropped
+    assert_eq!(data.trail.len(), TRAIL_LENGTH);
+    assert_eq!(data.trail.front().unwrap().0, Point::new(10, 0x0488));
+    assert_eq!(*data.trail.back().unwrap(), (Point::new(TRAIL_LENGTH as i32 + 9, 0x0488), Point::new(7, 0x15)));
+}
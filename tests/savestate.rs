@@ -0,0 +1,81 @@
+use smtileviewer::memorysource::WRAM_SIZE;
+use smtileviewer::savestate::{bsnes_decode, find_wram, rle_decode, snes9x_wram};
+
+mod common;
+use common::GameWram;
+
+// WRAM of a 3x2 screens room, what find_wram looks for
+fn room_wram() -> Vec<u8> {
+    GameWram::new(0x30, 0x20).wram
+}
+
+fn snes9x_state(blocks : &[(&str, &[u8])]) -> Vec<u8> {
+    let mut state = b"#!s9xsnp:0011\n".to_vec();
+    for (name, data) in blocks {
+        state.extend(format!("{}:{:06}:", name, data.len()).as_bytes());
+        state.extend(*data);
+    }
+    state
+}
+
+#[test]
+fn snes9x_ram_block() {
+    let wram = room_wram();
+    let state = snes9x_state(&[("NAM", b"sm.sfc"), ("CPU", &[1, 2, 3]), ("RAM", &wram), ("VRA", &[0; 16])]);
+    assert_eq!(snes9x_wram(&state).unwrap(), wram);
+}
+
+#[test]
+fn broken_snes9x_states() {
+    // Not a savestate at all
+    assert!(snes9x_wram(b"#!s9xsnp").is_err());
+    // Invalid UTF-8 in the block header
+    let mut state = b"#!s9xsnp:0011\n".to_vec();
+    state.extend([0xFF; 16]);
+    assert!(snes9x_wram(&state).is_err());
+    // The file stops in the middle of the RAM block
+    let wram = room_wram();
+    let mut state = snes9x_state(&[("CPU", &[1, 2, 3]), ("RAM", &wram)]);
+    state.truncate(state.len() - 100);
+    assert!(snes9x_wram(&state).is_err());
+    // No RAM block
+    assert!(snes9x_wram(&snes9x_state(&[("CPU", &[1, 2, 3])])).is_err());
+}
+
+#[test]
+fn nall_rle() {
+    // Size on 8 bytes, 3 bytes copied then 7 repeated
+    let mut input = 10u64.to_le_bytes().to_vec();
+    input.extend([0x02, 1, 2, 3, 0x83, 7]);
+    assert_eq!(rle_decode(&input), vec![1, 2, 3, 7, 7, 7, 7, 7, 7, 7]);
+    // Missing data is zeroes, the size is what the header says
+    let mut input = 5u64.to_le_bytes().to_vec();
+    input.extend([0x04, 9]);
+    assert_eq!(rle_decode(&input), vec![9, 0, 0, 0, 0]);
+}
+
+#[test]
+fn bsnes_state() {
+    let mut rle = 6u64.to_le_bytes().to_vec();
+    rle.extend([0x05, b'B', b'S', b'T', b'1', 0xAA, 0xBB]);
+    let mut state = vec![];
+    state.extend(115u32.to_le_bytes());
+    state.extend((rle.len() as u32).to_le_bytes());
+    state.extend(0u32.to_le_bytes());
+    state.extend(&rle);
+    assert_eq!(bsnes_decode(&state).unwrap(), b"BST1\xAA\xBB".to_vec());
+    // The serializer size goes past the end of the file
+    state.truncate(state.len() - 1);
+    assert!(bsnes_decode(&state).is_err());
+    assert!(bsnes_decode(&[0; 8]).is_err());
+}
+
+#[test]
+fn wram_position() {
+    let mut state = vec![0xEE; 5];
+    state.extend(room_wram());
+    state.extend([0; 40]);
+    assert_eq!(find_wram(&state), Some(5));
+    assert_eq!(find_wram(&[0; 100]), None);
+    assert_eq!(find_wram(&vec![0; WRAM_SIZE + 10]), None);
+}
@@ -0,0 +1,129 @@
+use smtileviewer::data::{slope_shape, slope_columns};
+use smtileviewer::rom::Rom;
+
+// Even-odd test on a point of the tile
+fn inside(shape : &[(i32, i32)], px : f32, py : f32) -> bool {
+    let mut inside = false;
+    for i in 0..shape.len() {
+        let (x1, y1) = (shape[i].0 as f32, shape[i].1 as f32);
+        let (x2, y2) = (shape[(i + 1) % shape.len()].0 as f32, shape[(i + 1) % shape.len()].1 as f32);
+        if (y1 <= py) != (y2 <= py) && px < x1 + (py - y1) * (x2 - x1) / (y2 - y1) {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// A pixel is solid when most of it is inside, the samples are symmetric so flips give the same result
+fn solid(shape : &[(i32, i32)], x : i32, y : i32) -> bool {
+    let samples = [(0.3, 0.4), (0.7, 0.4), (0.3, 0.6), (0.7, 0.6)];
+    samples.iter().filter(|&&(dx, dy)| inside(shape, x as f32 + dx, y as f32 + dy)).count() >= 2
+}
+
+fn solid_pixels(bts : u8) -> usize {
+    let shape = slope_shape(bts).unwrap();
+    (0..16).flat_map(|y| (0..16).map(move |x| (x, y))).filter(|&(x, y)| solid(&shape, x, y)).count()
+}
+
+// Checks the solid pixel count, the solid and empty corners, then that every flip mirrors the shape
+fn check(slope : u8, pixels : usize, solid_corners : &[(i32, i32)], empty_corners : &[(i32, i32)]) {
+    let shape = slope_shape(slope).unwrap_or_else(|| panic!("No shape for slope {:02X}", slope));
+    for &(x, y) in shape.iter() {
+        assert!((0..=16).contains(&x) && (0..=16).contains(&y), "Slope {:02X} goes out of the tile", slope);
+    }
+    assert_eq!(solid_pixels(slope), pixels, "Slope {:02X} size", slope);
+    for &(x, y) in solid_corners {
+        assert!(solid(&shape, x, y), "Slope {:02X} should be solid at {},{}", slope, x, y);
+    }
+    for &(x, y) in empty_corners {
+        assert!(!solid(&shape, x, y), "Slope {:02X} should be empty at {},{}", slope, x, y);
+    }
+    let h_flip = slope_shape(slope | 0x40).unwrap();
+    let v_flip = slope_shape(slope | 0x80).unwrap();
+    let hv_flip = slope_shape(slope | 0xC0).unwrap();
+    for y in 0..16 {
+        for x in 0..16 {
+            let expected = solid(&shape, x, y);
+            assert_eq!(solid(&h_flip, 15 - x, y), expected, "Slope {:02X} h-flip at {},{}", slope, x, y);
+            assert_eq!(solid(&v_flip, x, 15 - y), expected, "Slope {:02X} v-flip at {},{}", slope, x, y);
+            assert_eq!(solid(&hv_flip, 15 - x, 15 - y), expected, "Slope {:02X} hv-flip at {},{}", slope, x, y);
+        }
+    }
+}
+
+const TOP_LEFT : (i32, i32) = (0, 0);
+const TOP_RIGHT : (i32, i32) = (15, 0);
+const BOTTOM_LEFT : (i32, i32) = (0, 15);
+const BOTTOM_RIGHT : (i32, i32) = (15, 15);
+
+#[test]
+fn half_blocks() {
+    check(0x00, 128, &[BOTTOM_LEFT, BOTTOM_RIGHT, (0, 8)], &[TOP_LEFT, TOP_RIGHT, (0, 7)]);
+    check(0x01, 128, &[TOP_RIGHT, BOTTOM_RIGHT, (8, 0)], &[TOP_LEFT, BOTTOM_LEFT, (7, 0)]);
+}
+
+#[test]
+fn quarter_blocks() {
+    check(0x02, 64, &[BOTTOM_RIGHT, (8, 8)], &[TOP_LEFT, TOP_RIGHT, BOTTOM_LEFT]);
+    check(0x03, 192, &[TOP_RIGHT, BOTTOM_LEFT, BOTTOM_RIGHT], &[TOP_LEFT, (7, 7)]);
+}
+
+#[test]
+fn full_blocks() {
+    check(0x07, 256, &[TOP_LEFT, TOP_RIGHT, BOTTOM_LEFT, BOTTOM_RIGHT], &[]);
+    check(0x13, 256, &[TOP_LEFT, TOP_RIGHT, BOTTOM_LEFT, BOTTOM_RIGHT], &[]);
+}
+
+#[test]
+fn slopes_45() {
+    check(0x12, 136, &[TOP_RIGHT, BOTTOM_RIGHT, (1, 15)], &[TOP_LEFT, (0, 14)]);
+    check(0x14, 36, &[BOTTOM_RIGHT, (15, 8)], &[TOP_LEFT, TOP_RIGHT, BOTTOM_LEFT, (15, 7)]);
+    check(0x15, 228, &[TOP_RIGHT, BOTTOM_LEFT, BOTTOM_RIGHT, (0, 8)], &[TOP_LEFT, (0, 6)]);
+}
+
+#[test]
+fn gentle_slopes() {
+    check(0x16, 64, &[BOTTOM_RIGHT, (15, 8)], &[TOP_LEFT, TOP_RIGHT, BOTTOM_LEFT, (15, 7)]);
+    check(0x17, 192, &[TOP_RIGHT, BOTTOM_LEFT, BOTTOM_RIGHT, (0, 8)], &[TOP_LEFT, (0, 7)]);
+    // The middle one must continue the lower one and end where the upper one starts
+    check(0x18, 41, &[BOTTOM_RIGHT, (15, 11)], &[TOP_LEFT, TOP_RIGHT, BOTTOM_LEFT, (15, 10)]);
+    check(0x19, 128, &[BOTTOM_LEFT, BOTTOM_RIGHT, (0, 11), (15, 5)], &[TOP_LEFT, TOP_RIGHT, (0, 10), (15, 4)]);
+    check(0x1A, 217, &[TOP_RIGHT, BOTTOM_LEFT, BOTTOM_RIGHT, (0, 5)], &[TOP_LEFT, (0, 4)]);
+}
+
+#[test]
+fn steep_slopes() {
+    check(0x1B, 192, &[TOP_RIGHT, BOTTOM_LEFT, BOTTOM_RIGHT, (8, 0)], &[TOP_LEFT, (7, 0)]);
+    check(0x1C, 64, &[(15, 2), BOTTOM_RIGHT, (8, 15)], &[TOP_LEFT, BOTTOM_LEFT, (7, 15)]);
+    check(0x1D, 219, &[TOP_RIGHT, BOTTOM_LEFT, BOTTOM_RIGHT, (5, 0)], &[TOP_LEFT, (4, 0)]);
+    check(0x1E, 130, &[TOP_RIGHT, BOTTOM_RIGHT, (5, 15), (11, 0)], &[TOP_LEFT, BOTTOM_LEFT, (4, 15), (10, 0)]);
+    check(0x1F, 43, &[(15, 3), BOTTOM_RIGHT, (11, 15)], &[TOP_LEFT, BOTTOM_LEFT, (10, 15)]);
+}
+
+#[test]
+fn unused_slopes_have_no_shape() {
+    for slope in [0x04, 0x05, 0x06, 0x08, 0x0F, 0x11] {
+        assert!(slope_shape(slope).is_none(), "Slope {:02X} has a shape", slope);
+    }
+}
+
+#[test]
+fn slope_heights_from_rom() {
+    let mut rom = Rom::new();
+    assert!(slope_columns(&rom, 0x12).is_none());
+    // Slope 0x12 climbs one pixel per column, the table holds the top of each column
+    let mut bank = vec![0u8; 0x8000];
+    for x in 0..16 {
+        bank[0x0B2B + 0x12 * 16 + x] = 15 - x as u8;
+    }
+    rom.set_bank(0x94, bank);
+    let expected : Vec<u8> = (1..=16).collect();
+    assert_eq!(slope_columns(&rom, 0x12).unwrap().to_vec(), expected);
+    // The vertical flip is done when drawing, the columns stay the same
+    assert_eq!(slope_columns(&rom, 0x92).unwrap().to_vec(), expected);
+    let flipped : Vec<u8> = (1..=16).rev().collect();
+    assert_eq!(slope_columns(&rom, 0x52).unwrap().to_vec(), flipped);
+    assert_eq!(slope_columns(&rom, 0x13).unwrap().to_vec(), vec![16; 16]);
+    // Square slopes are drawn from their shape
+    assert!(slope_columns(&rom, 0x01).is_none());
+}
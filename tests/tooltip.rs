@@ -0,0 +1,50 @@
+use smtileviewer::data::SharedData;
+use smtileviewer::render::{tile_at, tile_tooltip_lines};
+use smtileviewer::wsthread::actually_getting_data;
+
+mod common;
+use common::GameWram;
+
+// Layer 1 at 0,0 puts the room 128,112 pixels into the game area
+fn tooltip(game : GameWram, x : i32, y : i32) -> Vec<String> {
+    let mut data = SharedData::new();
+    actually_getting_data(&mut game.image(), &mut data).unwrap();
+    let a = tile_at(&data, 128 + x * 16 + 8, 112 + y * 16 + 8).expect("No tile under the mouse");
+    tile_tooltip_lines(&data, a)
+}
+
+#[test]
+fn slope() {
+    let mut game = GameWram::new(4, 3);
+    game.tile(3, 1, 0x1012, 0x52);
+    let lines = tooltip(game, 3, 1);
+    assert_eq!(lines[0], "Tile 3,1 - index $0007");
+    assert_eq!(lines[1], "Slope - clip $1012 BTS $52");
+    assert_eq!(lines[2], "Slope $12, h-flip");
+    assert_eq!(lines[3], "Clip $7F:0010 BTS $7F:6409");
+}
+
+#[test]
+fn breakable() {
+    let mut game = GameWram::new(4, 3);
+    game.tile(1, 2, 0xC000, 0x05);
+    let lines = tooltip(game, 1, 2);
+    assert_eq!(lines[1], "Shot block - clip $C000 BTS $05");
+    assert_eq!(lines[2], "2x1 shot block, not respawning");
+    // An unknown BTS is still shown whole
+    let mut game = GameWram::new(4, 3);
+    game.tile(2, 2, 0xC000, 0x48);
+    let lines = tooltip(game, 2, 2);
+    assert_eq!(lines[1], "Shot block - clip $C000 BTS $48");
+    assert_eq!(lines[2], "Shot block $48");
+}
+
+#[test]
+fn raw_only() {
+    // The BTS of a solid block means nothing, it is only shown raw
+    let mut game = GameWram::new(4, 3);
+    game.tile(0, 0, 0x8000, 0x07);
+    let lines = tooltip(game, 0, 0);
+    assert_eq!(lines[1], "Solid - clip $8000 BTS $07");
+    assert_eq!(lines[2], "Clip $7F:0002 BTS $7F:6402");
+}
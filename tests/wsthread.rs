@@ -0,0 +1,238 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use smtileviewer::data::{GamePhase, SharedData, Usb2SnesError, View};
+use smtileviewer::memorysource::MemoryImage;
+use smtileviewer::mockserver::{MockClient, MockServer};
+use smtileviewer::projectile::ProjectileKind;
+use smtileviewer::render;
+use smtileviewer::snapshot::FrameChannel;
+use smtileviewer::wsthread;
+
+mod common;
+use common::{GameWram, set_word};
+
+const RETRY_DELAY : Duration = Duration::from_millis(100);
+
+fn wait_for(data : &FrameChannel, what : &str, check : impl Fn(&SharedData) -> bool) {
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(10) {
+        if check(&data.latest()) {
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+    panic!("Timeout waiting for {}", what);
+}
+
+fn error_is(data : &SharedData, error : Usb2SnesError) -> bool {
+    !data.usb2snes_ready && std::mem::discriminant(&data.usb2snes_error) == std::mem::discriminant(&error)
+}
+
+fn game_wram() -> Vec<u8> {
+    let mut game = GameWram::new(0x90, 0x58);
+    game.samus(0x0480, 0x0488, 0x0007, 0x0015).word(0x07B5, 0x8936).tile(0, 0, 0x8123, 0);
+    // Enemy in the third slot
+    game.word(0x0F78 + 0x80, 0xCEBF).word(0x0F78 + 0x82, 0x0500).word(0x0F78 + 0x86, 0x0490)
+        .word(0x0F78 + 0x8A, 0x0008).word(0x0F78 + 0x8C, 0x000C).word(0x0F78 + 0x94, 20);
+    // Missile in the second projectile slot
+    game.word(0x0B64 + 2, 0x04A0).word(0x0B78 + 2, 0x0480).word(0x0BB4 + 2, 0x0008).word(0x0BC8 + 2, 0x0004).word(0x0C18 + 2, 0x0100);
+    // Item PLM on the tile 5,1 and an enemy shot
+    game.word(0x1C37 + 4, 0xEED7).word(0x1C87 + 4, (0x90 + 5) * 2);
+    game.word(0x1997, 0x8BC2).word(0x1A4B, 0x0520).word(0x1A93, 0x0498).word(0x1BB3, 0x0004).word(0x1BD7, 0x0006);
+    // Scrolls of the first screens and the camera
+    game.byte(0xCD20, 0x02).byte(0xCD21, 0x01).layer1(0x0400, 0x0400);
+    // Subpixels, speeds and pose
+    game.word(0x0AF8, 0x1A00).word(0x0AFC, 0xFFFF).word(0x0B2C, 0x8000).word(0x0B2E, 0x0004)
+        .word(0x0B42, 0x0002).word(0x0B44, 0x1000).word(0x0B46, 0x0001).word(0x0B48, 0x4000)
+        .word(0x0A1C, 0x0009).byte(0x0A1E, 0x08).byte(0x0A1F, 0x01);
+    game.frame_counter(1234);
+    game.wram
+}
+
+// Start the polling thread on a mock server
+fn start_polling(server : &MockServer) -> Arc<FrameChannel> {
+    let data = Arc::new(FrameChannel::new(SharedData::new()));
+    let data_clone = Arc::clone(&data);
+    let address = server.address();
+    std::thread::spawn(move || wsthread::wsthread_with(|| MockClient::connect(address), data_clone, None, RETRY_DELAY));
+    data
+}
+
+// Mock server running the game, with the polling thread reading it
+fn start_game() -> (MockServer, Arc<FrameChannel>) {
+    let server = MockServer::start(MemoryImage::new(game_wram(), vec![])).expect("Can't start the mock server");
+    {
+        let mut state = server.state();
+        state.devices = vec![String::from("SD2SNES COM3")];
+        state.game = String::from("/Super Metroid.sfc");
+    }
+    let data = start_polling(&server);
+    wait_for(&data, "ready", |d| d.usb2snes_ready);
+    wait_for(&data, "the level data", |d| !d.map_data.is_empty());
+    wait_for(&data, "the enemies", |d| !d.enemies.is_empty());
+    (server, data)
+}
+
+// The real client only connects to the usb2snes port, this test needs it free
+#[test]
+fn sync_client() {
+    let server = match MockServer::start_on(23074, MemoryImage::new(game_wram(), vec![])) {
+        Ok(server) => server,
+        Err(err) => {
+            println!("Skipped, can't listen on the usb2snes port : {}", err);
+            return;
+        }
+    };
+    let data = Arc::new(FrameChannel::new(SharedData::new()));
+    let data_clone = Arc::clone(&data);
+    std::thread::spawn(move || wsthread::wsthread(data_clone, None, RETRY_DELAY));
+    wait_for(&data, "NoDevice", |d| error_is(d, Usb2SnesError::NoDevice));
+    server.state().devices = vec![String::from("SD2SNES COM3")];
+    wait_for(&data, "NoGame", |d| error_is(d, Usb2SnesError::NoGame));
+    server.state().game = String::from("/Super Metroid.sfc");
+    wait_for(&data, "ready", |d| d.usb2snes_ready);
+    wait_for(&data, "the level data", |d| !d.map_data.is_empty());
+    let data = data.latest();
+    assert_eq!((data.samus_pos.x, data.samus_pos.y), (0x0480, 0x0488));
+    assert_eq!(data.map_data[2], 0x23);
+    assert_eq!(data.enemies.len(), 1);
+}
+
+#[test]
+fn cant_connect() {
+    // Nothing listens on the port of a closed listener
+    let address = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let data = Arc::new(FrameChannel::new(SharedData::new()));
+    let data_clone = Arc::clone(&data);
+    std::thread::spawn(move || wsthread::wsthread_with(|| MockClient::connect(address), data_clone, None, RETRY_DELAY));
+    wait_for(&data, "CantConnect", |d| error_is(d, Usb2SnesError::CantConnect));
+}
+
+#[test]
+fn waits_for_a_device_and_a_game() {
+    let server = MockServer::start(MemoryImage::new(game_wram(), vec![])).expect("Can't start the mock server");
+    let data = start_polling(&server);
+    wait_for(&data, "NoDevice", |d| error_is(d, Usb2SnesError::NoDevice));
+
+    server.state().fail_device_list = true;
+    wait_for(&data, "CantAttach", |d| error_is(d, Usb2SnesError::CantAttach));
+
+    {
+        let mut state = server.state();
+        state.fail_device_list = false;
+        state.devices = vec![String::from("SD2SNES COM3")];
+    }
+    wait_for(&data, "NoGame with menu.bin", |d| error_is(d, Usb2SnesError::NoGame));
+
+    // Two more answers to Info, the last one is for m3nu.bin
+    server.state().game = String::from("/boot/m3nu.bin");
+    let version = data.latest().version;
+    wait_for(&data, "NoGame with m3nu.bin", |d| d.version >= version + 2);
+    assert!(error_is(&data.latest(), Usb2SnesError::NoGame));
+
+    server.state().game = String::from("/Super Metroid.sfc");
+    wait_for(&data, "ready", |d| d.usb2snes_ready);
+}
+
+#[test]
+fn reads_the_game() {
+    let (_server, data) = start_game();
+    let data = data.latest();
+    assert_eq!(data.samus_pos.x, 0x0480);
+    assert_eq!(data.samus_pos.y, 0x0488);
+    assert_eq!(data.radius.x, 0x0007);
+    assert_eq!(data.radius.y, 0x0015);
+    assert_eq!(data.width, 0x0090);
+    assert_eq!(data.door_stuff, 0x8936);
+    assert_eq!(data.map_data.len(), 0x10000);
+    assert_eq!(data.map_data[2], 0x23);
+    assert_eq!(data.map_data[3], 0x81);
+    // Each decoder has its own tests, this checks that every table of the poll lands in its place
+    assert_eq!((data.enemies.len(), data.enemies[0].slot, data.enemies[0].health), (1, 2, 20));
+    assert_eq!((data.projectiles.len(), data.projectiles[0].slot, data.projectiles[0].kind()), (1, 1, ProjectileKind::Missile));
+    assert_eq!((data.plms.len(), data.plms[0].tile_index()), (1, 0x90 + 5));
+    assert_eq!((data.enemy_projectiles.len(), data.enemy_projectiles[0].radius_y), (1, 6));
+    assert_eq!(&data.scrolls[0..3], &[0x02, 0x01, 0x00]);
+    assert_eq!((data.layer1.x, data.camera.y), (0x0400, 0x0400 - 112));
+    let movement = &data.movement;
+    assert_eq!((movement.subpixel_x, movement.submomentum_x, movement.pose, movement.movement_type), (0x1A00, 0x4000, 0x0009, 0x01));
+    assert!(render::status_lines(&data, false).iter().any(|line| line.ends_with("Pose $09 Running - Running facing right")));
+    assert_eq!(data.frame_counter, Some(1234));
+    assert_eq!((data.game_phase, data.loaded_room), (GamePhase::Gameplay, Some(0x91F8)));
+}
+
+#[test]
+fn refreshes_broken_blocks() {
+    let (server, data) = start_game();
+    // A block breaks in the visible rows, it is read again once the game moves on
+    let a = 60 * 0x90 + 10;
+    {
+        let mut state = server.state();
+        set_word(&mut state.memory.wram, 0x10002 + a * 2, 0xB000);
+        set_word(&mut state.memory.wram, 0x05B6, 1300);
+    }
+    wait_for(&data, "the broken block", |d| render::tile_changed(d, a));
+    let data = data.latest();
+    assert_eq!(render::tile_clip(&data, a), 0xB000);
+    assert!(!render::tile_changed(&data, a + 1));
+}
+
+#[test]
+fn refreshes_the_drawn_view() {
+    let (server, data) = start_game();
+    // This row is only seen by the camera centered on Samus
+    data.set_view(View::Samus);
+    wait_for(&data, "the Samus view", |d| d.view == View::Samus);
+    let a = 86 * 0x90 + 10;
+    {
+        let mut state = server.state();
+        set_word(&mut state.memory.wram, 0x10002 + a * 2, 0xB000);
+        set_word(&mut state.memory.wram, 0x05B6, 1300);
+    }
+    wait_for(&data, "the block under the Samus camera", |d| render::tile_changed(d, a));
+    // The whole room is read a part at a time when it is all drawn
+    data.set_view(View::Room);
+    wait_for(&data, "the room view", |d| d.view == View::Room);
+    let a = 70 * 0x90 + 10;
+    set_word(&mut server.state().memory.wram, 0x10002 + a * 2, 0xB000);
+    for (i, counter) in [1400u16, 1410, 1420].iter().enumerate() {
+        set_word(&mut server.state().memory.wram, 0x05B6, *counter);
+        wait_for(&data, "a refresh of the room", |d| d.level_refreshed_at == Some(*counter));
+        assert_eq!(render::tile_changed(&data.latest(), a), i == 2);
+    }
+}
+
+#[test]
+fn reloads_the_room_after_a_door() {
+    let (server, data) = start_game();
+    let a = 60 * 0x90 + 10;
+    {
+        let mut state = server.state();
+        set_word(&mut state.memory.wram, 0x10002 + a * 2, 0xB000);
+        set_word(&mut state.memory.wram, 0x05B6, 1300);
+    }
+    wait_for(&data, "the broken block", |d| render::tile_changed(d, a));
+    let dropped_frames = data.latest().dropped_frames;
+    // Going through a door back into the same room reads its level data again,
+    // the frames of the transition are followed one by one
+    {
+        let mut state = server.state();
+        state.memory.wram[0x0998] = 0x0B;
+        set_word(&mut state.memory.wram, 0x05B6, 1301);
+    }
+    wait_for(&data, "the door transition", |d| d.game_phase == GamePhase::DoorTransition && d.loaded_room.is_none() && d.frame_counter == Some(1301));
+    set_word(&mut server.state().memory.wram, 0x05B6, 1302);
+    wait_for(&data, "the next frame of the transition", |d| d.frame_counter == Some(1302));
+    {
+        let mut state = server.state();
+        set_word(&mut state.memory.wram, 0x10002, 0x8042);
+        set_word(&mut state.memory.wram, 0x05B6, 1303);
+        state.memory.wram[0x0998] = 0x08;
+    }
+    wait_for(&data, "the room after the door", |d| d.loaded_room == Some(0x91F8) && d.map_data[2] == 0x42);
+    let data = data.latest();
+    assert!(!render::tile_changed(&data, a));
+    assert_eq!(data.dropped_frames, dropped_frames);
+    // Only the frame after the door is left of the trail
+    assert_eq!(data.trail.len(), 1);
+}